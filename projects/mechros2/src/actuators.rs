@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{info, debug, warn, error};
 use tokio::time::{Duration, sleep};
 use crate::node_manager::MechNodeManager;
+use crate::sensors::proximity::ProximityReading;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActuatorCommands {
@@ -123,7 +124,7 @@ impl ActuatorController {
         Ok(())
     }
 
-    pub async fn execute_commands(&self, commands: ActuatorCommands) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn execute_commands(&self, mut commands: ActuatorCommands) -> Result<(), Box<dyn std::error::Error>> {
         debug!("🎯 Ejecutando comandos de actuadores...");
 
         // Verificar parada de emergencia
@@ -132,12 +133,35 @@ impl ActuatorController {
             return self.emergency_stop().await;
         }
 
+        // Tras una parada de emergencia no se acepta movimiento hasta rearmar
+        if self.safety_monitor.emergency_active().await {
+            debug!("🛑 Parada de emergencia enclavada, comandos ignorados");
+            return Ok(());
+        }
+
         // Verificar seguridad
         if !self.safety_monitor.is_safe(&commands).await? {
             warn!("⚠️ Comandos rechazados por monitor de seguridad");
             return Ok(());
         }
 
+        // Limitación de velocidad impuesta por alarmas (p. ej. ambientales)
+        let speed_factor = self.safety_monitor.speed_factor().await;
+        if speed_factor < 1.0 {
+            if speed_factor <= 0.0 {
+                debug!("🛑 Parada segura activa, motores detenidos");
+                commands.motor_speeds = None;
+                commands.linear_velocity = None;
+                commands.angular_velocity = None;
+                self.motor_controller.stop_all().await?;
+            }
+            commands.linear_velocity = commands.linear_velocity.map(|v| v * speed_factor);
+            commands.angular_velocity = commands.angular_velocity.map(|v| v * speed_factor);
+            commands.motor_speeds = commands.motor_speeds.map(|speeds| {
+                speeds.iter().map(|s| s * speed_factor as f32).collect()
+            });
+        }
+
         // Ejecutar comandos en paralelo
         let motor_task = async {
             if let Some(ref speeds) = commands.motor_speeds {
@@ -202,8 +226,18 @@ impl ActuatorController {
         Ok(())
     }
 
+    pub async fn update_proximity(&self, readings: &[ProximityReading]) {
+        self.safety_monitor.update_proximity(readings).await;
+    }
+
+    /// Factor de velocidad máxima (0 = parada segura, 1 = sin limitación).
+    pub async fn set_speed_factor(&self, factor: f64) {
+        self.safety_monitor.set_speed_factor(factor).await;
+    }
+
     pub async fn emergency_stop(&self) -> Result<(), Box<dyn std::error::Error>> {
         error!("🚨 EJECUTANDO PARADA DE EMERGENCIA");
+        self.safety_monitor.set_emergency(true).await;
 
         // Parar todos los motores inmediatamente
         self.motor_controller.stop_all().await?;
@@ -240,6 +274,14 @@ impl ActuatorController {
         Ok(())
     }
 
+    /// Rearma los actuadores tras una parada de emergencia (acción del operador).
+    pub async fn reset_emergency_stop(&self) {
+        if self.safety_monitor.emergency_active().await {
+            info!("🔓 Parada de emergencia rearmada");
+            self.safety_monitor.set_emergency(false).await;
+        }
+    }
+
     pub async fn get_status(&self) -> ActuatorStatus {
         ActuatorStatus {
             motors_online: self.motor_controller.is_online().await,
@@ -248,7 +290,7 @@ impl ActuatorController {
             leds_online: self.led_controller.is_online().await,
             speaker_online: self.speaker_controller.is_online().await,
             safety_system_active: self.safety_monitor.is_active().await,
-            emergency_stop_active: false, // Se actualizaría dinámicamente
+            emergency_stop_active: self.safety_monitor.emergency_active().await,
         }
     }
 }
@@ -495,6 +537,10 @@ pub struct SafetyMonitor {
     active: bool,
     max_linear_velocity: f64,
     max_angular_velocity: f64,
+    min_obstacle_distance: f32,
+    proximity: tokio::sync::RwLock<Vec<ProximityReading>>,
+    speed_factor: tokio::sync::RwLock<f64>,
+    emergency_latched: tokio::sync::RwLock<bool>,
 }
 
 impl SafetyMonitor {
@@ -503,6 +549,10 @@ impl SafetyMonitor {
             active: false,
             max_linear_velocity: 2.0, // m/s
             max_angular_velocity: 1.0, // rad/s
+            min_obstacle_distance: 0.2, // m
+            proximity: tokio::sync::RwLock::new(Vec::new()),
+            speed_factor: tokio::sync::RwLock::new(1.0),
+            emergency_latched: tokio::sync::RwLock::new(false),
         })
     }
 
@@ -527,6 +577,19 @@ impl SafetyMonitor {
             }
         }
 
+        // Verificar obstáculos cercanos en la dirección de avance
+        if let Some(linear_vel) = commands.linear_velocity {
+            let proximity = self.proximity.read().await;
+            for reading in proximity.iter() {
+                let Some(point) = reading.detection else { continue };
+                if reading.range < self.min_obstacle_distance && linear_vel.dot(&point.coords) > 0.0 {
+                    warn!("⚠️ Obstáculo a {:.2}m en {} en la dirección de avance",
+                          reading.range, reading.sensor_id);
+                    return Ok(false);
+                }
+            }
+        }
+
         // Verificar velocidades angulares
         if let Some(angular_vel) = commands.angular_velocity {
             if angular_vel.magnitude() > self.max_angular_velocity {
@@ -549,6 +612,31 @@ impl SafetyMonitor {
         Ok(true)
     }
 
+    async fn update_proximity(&self, readings: &[ProximityReading]) {
+        *self.proximity.write().await = readings.to_vec();
+    }
+
+    async fn set_speed_factor(&self, factor: f64) {
+        let factor = factor.clamp(0.0, 1.0);
+        let mut current = self.speed_factor.write().await;
+        if (*current - factor).abs() > f64::EPSILON {
+            info!("🛡️ Factor de velocidad: {:.0}% -> {:.0}%", *current * 100.0, factor * 100.0);
+            *current = factor;
+        }
+    }
+
+    async fn speed_factor(&self) -> f64 {
+        *self.speed_factor.read().await
+    }
+
+    async fn set_emergency(&self, latched: bool) {
+        *self.emergency_latched.write().await = latched;
+    }
+
+    async fn emergency_active(&self) -> bool {
+        *self.emergency_latched.read().await
+    }
+
     async fn is_active(&self) -> bool {
         self.active
     }
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{info, warn, error, debug};
-use nalgebra::{Vector3, Point3};
+use nalgebra::{Vector3, Point3, UnitQuaternion, Isometry3, Translation3};
 use serde::{Deserialize, Serialize};
 
 pub mod node_manager;
@@ -16,6 +16,12 @@ pub mod vision;
 
 use node_manager::MechNodeManager;
 use sensors::SensorHub;
+use sensors::alarms::AlarmAction;
+use sensors::battery::{BatteryAlert, BatteryEstimate};
+use sensors::calibration::{ImuCalibrationConfig, ImuCalibrationStep};
+use sensors::health::{SensorHealthState, SensorKind};
+use sensors::odometry::OdometryEstimate;
+use sensors::simulation::SimulationConfig;
 use actuators::ActuatorController;
 use navigation::NavigationPlanner;
 use vision::VisionProcessor;
@@ -26,20 +32,39 @@ pub struct SystemState {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub position: Point3<f64>,
     pub velocity: Vector3<f64>,
-    pub orientation: Vector3<f64>, // Euler angles
+    #[serde(with = "sensors::orientation::rpy_serde")]
+    pub orientation: UnitQuaternion<f64>, // Publicado como {roll, pitch, yaw}
     pub battery_level: f32,
     pub system_status: SystemStatus,
 }
 
+impl SystemState {
+    /// Pose del robot en el marco mundo.
+    pub fn pose(&self) -> Isometry3<f64> {
+        Isometry3::from_parts(Translation3::from(self.position.coords), self.orientation)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SystemStatus {
     Initializing,
     Ready,
     Active,
+    LowBattery(f32),
+    CriticalBattery(f32),
     Error(String),
+    Fault(Vec<SystemFault>), // Paradas de seguridad activas; se vuelve a activo al levantarlas todas
     Shutdown,
 }
 
+/// Causa tipada de una parada de seguridad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemFault {
+    Environment, // Alarma ambiental crítica; se levanta al recuperarse las condiciones
+    ImuFailure,  // Sin IMU no hay orientación fiable; se levanta cuando el IMU se recupera
+    Incident,    // Vuelco, caída libre o colisión; solo lo levanta el rearme del operador
+}
+
 // 🚀 Main MechROS2 Hub
 pub struct MechROS2Hub {
     node_manager: Arc<MechNodeManager>,
@@ -52,10 +77,17 @@ pub struct MechROS2Hub {
 
 impl MechROS2Hub {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_imu_calibration(ImuCalibrationConfig::default()).await
+    }
+
+    /// Crea el hub leyendo y guardando la calibración del IMU en `imu_calibration.file_path`.
+    pub async fn with_imu_calibration(imu_calibration: ImuCalibrationConfig) -> Result<Self, Box<dyn std::error::Error>> {
         info!("🚀 Inicializando MechROS2 Hub...");
 
         let node_manager = Arc::new(MechNodeManager::new().await?);
-        let sensor_hub = Arc::new(SensorHub::new(node_manager.clone()).await?);
+        let sensor_hub = Arc::new(
+            SensorHub::with_imu_calibration(node_manager.clone(), SimulationConfig::default(), imu_calibration).await?,
+        );
         let actuator_controller = Arc::new(ActuatorController::new(node_manager.clone()).await?);
         let navigation_planner = Arc::new(NavigationPlanner::new(node_manager.clone()).await?);
         let vision_processor = Arc::new(VisionProcessor::new(node_manager.clone()).await?);
@@ -64,7 +96,7 @@ impl MechROS2Hub {
             timestamp: chrono::Utc::now(),
             position: Point3::origin(),
             velocity: Vector3::zeros(),
-            orientation: Vector3::zeros(),
+            orientation: UnitQuaternion::identity(),
             battery_level: 100.0,
             system_status: SystemStatus::Initializing,
         };
@@ -111,17 +143,27 @@ impl MechROS2Hub {
         }
 
         // Tareas concurrentes
+        let acquisition_task = Arc::clone(&self.sensor_hub).run_acquisition();
         let sensor_task = self.run_sensor_loop();
         let navigation_task = self.run_navigation_loop();
         let vision_task = self.run_vision_loop();
         let state_publisher_task = self.run_state_publisher();
+        let sensor_event_task = self.run_sensor_event_loop();
+        let environment_alarm_task = self.run_environment_alarm_loop();
+        let incident_task = self.run_incident_loop();
+        let operator_task = self.run_operator_command_loop();
 
         // Ejecutar todas las tareas concurrentemente
         tokio::try_join!(
+            acquisition_task,
             sensor_task,
             navigation_task,
             vision_task,
-            state_publisher_task
+            state_publisher_task,
+            sensor_event_task,
+            environment_alarm_task,
+            incident_task,
+            operator_task
         )?;
 
         Ok(())
@@ -134,7 +176,7 @@ impl MechROS2Hub {
                     debug!("📊 Datos de sensores actualizados: {:?}", sensor_data);
 
                     // Actualizar estado del sistema con datos de sensores
-                    {
+                    let current_state = {
                         let mut state = self.system_state.write().await;
                         if let Some(pos) = sensor_data.position {
                             state.position = pos;
@@ -142,11 +184,46 @@ impl MechROS2Hub {
                         if let Some(vel) = sensor_data.velocity {
                             state.velocity = vel;
                         }
-                        if let Some(battery) = sensor_data.battery_level {
-                            state.battery_level = battery;
+                        if let Some(orientation) = sensor_data.orientation {
+                            state.orientation = orientation;
+                        }
+                        if let Some(battery) = &sensor_data.battery {
+                            state.battery_level = battery.state_of_charge as f32;
+                            if let Some(status) = battery_system_status(&state.system_status, battery) {
+                                state.system_status = status;
+                            }
+                        }
+                        // Parada segura mientras una alarma ambiental crítica siga activa
+                        if sensor_data.environment_action == AlarmAction::SafeStop {
+                            if let Some(status) = status_with_fault(&state.system_status, SystemFault::Environment) {
+                                error!("🌡️ Condiciones ambientales críticas, parada segura");
+                                state.system_status = status;
+                            }
+                        } else if let Some(status) = status_without_fault(&state.system_status, SystemFault::Environment) {
+                            info!("✅ Condiciones ambientales recuperadas");
+                            state.system_status = status;
                         }
                         state.timestamp = chrono::Utc::now();
+                        state.clone()
+                    };
+                    self.actuator_controller.set_speed_factor(sensor_data.environment_action.speed_factor()).await;
+
+                    // Obstáculos a partir del barrido LiDAR, proyectados con la pose del instante del barrido
+                    if let Some(scan) = &sensor_data.lidar_data {
+                        let scan_pose = match sensor_data.sample_timestamps.get(&SensorKind::Lidar) {
+                            Some(&scan_time) => self.pose_at(&current_state, sensor_data.odometry.as_ref(), scan_time),
+                            None => current_state.pose(),
+                        };
+                        if let Err(e) = self.navigation_planner.update_lidar_data(scan, &sensor_data.lidar_points, &scan_pose).await {
+                            warn!("⚠️  Error procesando LiDAR: {}", e);
+                        }
                     }
+
+                    // Sensores de proximidad: evasión y monitor de seguridad
+                    if let Err(e) = self.navigation_planner.update_proximity_data(&sensor_data.proximity_sensors, &current_state).await {
+                        warn!("⚠️  Error procesando proximidad: {}", e);
+                    }
+                    self.actuator_controller.update_proximity(&sensor_data.proximity_sensors).await;
                 }
                 Err(e) => {
                     warn!("⚠️  Error en sensores: {}", e);
@@ -157,6 +234,141 @@ impl MechROS2Hub {
         }
     }
 
+    /// Pose del robot en `at`, retrotrayendo la pose actual con el movimiento
+    /// medido por la odometría entre `at` y la última muestra.
+    fn pose_at(&self, current_state: &SystemState, latest_odometry: Option<&OdometryEstimate>, at: chrono::DateTime<chrono::Utc>) -> Isometry3<f64> {
+        let synced = self.sensor_hub.synchronize(at);
+        match (latest_odometry, synced.odometry) {
+            (Some(now), Some(then)) => current_state.pose() * (now.pose().inverse() * then.pose()),
+            _ => current_state.pose(),
+        }
+    }
+
+    async fn run_sensor_event_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut events = self.sensor_hub.subscribe_events();
+
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("⚠️  {} eventos de sensores descartados", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            if let Err(e) = self.node_manager.publish_sensor_event(&serde_json::to_string(&event)?).await {
+                warn!("⚠️  Error publicando evento de sensor: {}", e);
+            }
+
+            // Sin IMU no hay orientación fiable: el sistema pasa a error hasta que se recupere
+            if event.sensor == SensorKind::Imu {
+                let mut state = self.system_state.write().await;
+                match event.current {
+                    SensorHealthState::Failed => {
+                        if let Some(status) = status_with_fault(&state.system_status, SystemFault::ImuFailure) {
+                            error!("❌ IMU fuera de servicio: {:?}", event.detail);
+                            state.system_status = status;
+                        }
+                    }
+                    SensorHealthState::Ok => {
+                        if let Some(status) = status_without_fault(&state.system_status, SystemFault::ImuFailure) {
+                            info!("✅ IMU recuperado");
+                            state.system_status = status;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    async fn run_environment_alarm_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut alarms = self.sensor_hub.subscribe_alarms();
+
+        loop {
+            let alarm = match alarms.recv().await {
+                Ok(alarm) => alarm,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("⚠️  {} alarmas ambientales descartadas", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            if let Err(e) = self.node_manager.publish_environment_alarm(&serde_json::to_string(&alarm)?).await {
+                warn!("⚠️  Error publicando alarma ambiental: {}", e);
+            }
+        }
+    }
+
+    async fn run_incident_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut incidents = self.sensor_hub.subscribe_incidents();
+
+        loop {
+            let incident = match incidents.recv().await {
+                Ok(incident) => incident,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("⚠️  {} incidentes descartados", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            // Primero detener; el incidente queda activo hasta que el operador rearme
+            {
+                let mut state = self.system_state.write().await;
+                error!("💥 Incidente {:?}: parada de emergencia", incident.detection.kind);
+                if let Some(status) = status_with_fault(&state.system_status, SystemFault::Incident) {
+                    state.system_status = status;
+                }
+            }
+            if let Err(e) = self.actuator_controller.emergency_stop().await {
+                error!("❌ Error en parada de emergencia: {}", e);
+            }
+
+            if let Err(e) = self.node_manager.publish_incident(&serde_json::to_string(&incident)?).await {
+                warn!("⚠️  Error publicando incidente: {}", e);
+            }
+        }
+    }
+
+    /// Comandos del operador por `remote_commands`:
+    /// - `{"command": "reset_emergency_stop"}` rearma los actuadores y devuelve el
+    ///   sistema a activo tras un incidente.
+    /// - `{"command": "calibrate_imu", "step": ...}` ejecuta un paso de calibración
+    ///   del IMU (ver `ImuCalibrationStep`) y lo guarda en disco.
+    async fn run_operator_command_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let commands = self.node_manager.take_remote_commands(&["reset_emergency_stop", "calibrate_imu"]).await;
+            for command_str in commands {
+                let Ok(command) = serde_json::from_str::<serde_json::Value>(&command_str) else { continue };
+                match command["command"].as_str() {
+                    Some("reset_emergency_stop") => {
+                        self.actuator_controller.reset_emergency_stop().await;
+                        let mut state = self.system_state.write().await;
+                        if let Some(status) = status_without_fault(&state.system_status, SystemFault::Incident) {
+                            info!("✅ Parada de emergencia rearmada por el operador");
+                            state.system_status = status;
+                        }
+                    }
+                    Some("calibrate_imu") => {
+                        let result = match serde_json::from_value::<ImuCalibrationStep>(command) {
+                            Ok(step) => self.sensor_hub.calibrate_imu(step).await,
+                            Err(e) => Err(e.into()),
+                        };
+                        if let Err(e) = result {
+                            warn!("⚠️  Calibración de IMU fallida: {}", e);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            sleep(Duration::from_millis(100)).await; // 10Hz
+        }
+    }
+
     async fn run_navigation_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
         loop {
             let current_state = {
@@ -221,6 +433,52 @@ impl MechROS2Hub {
     }
 }
 
+// 🔋 Refleja las alertas de batería en el estado sin pisar errores ni apagado
+fn battery_system_status(current: &SystemStatus, battery: &BatteryEstimate) -> Option<SystemStatus> {
+    if !matches!(current, SystemStatus::Active | SystemStatus::LowBattery(_) | SystemStatus::CriticalBattery(_)) {
+        return None;
+    }
+
+    let level = battery.state_of_charge as f32;
+    let next = match battery.alert {
+        BatteryAlert::Normal => SystemStatus::Active,
+        BatteryAlert::Low => SystemStatus::LowBattery(level),
+        BatteryAlert::Critical => SystemStatus::CriticalBattery(level),
+    };
+
+    if std::mem::discriminant(current) != std::mem::discriminant(&next) {
+        match next {
+            SystemStatus::CriticalBattery(_) => error!("🪫 Batería crítica: {:.1}%", level),
+            SystemStatus::LowBattery(_) => warn!("🪫 Batería baja: {:.1}%", level),
+            _ => info!("🔋 Nivel de batería normal: {:.1}%", level),
+        }
+    }
+
+    Some(next)
+}
+
+// 🛑 Estado al activarse un fallo: se aplica desde cualquier estado operativo,
+// también con batería baja o crítica; los errores genéricos y el apagado se respetan
+fn status_with_fault(current: &SystemStatus, fault: SystemFault) -> Option<SystemStatus> {
+    match current {
+        SystemStatus::Error(_) | SystemStatus::Shutdown => None,
+        SystemStatus::Fault(faults) if faults.contains(&fault) => None,
+        SystemStatus::Fault(faults) => Some(SystemStatus::Fault(faults.iter().copied().chain([fault]).collect())),
+        _ => Some(SystemStatus::Fault(vec![fault])),
+    }
+}
+
+// ✅ Estado al levantarse un fallo: activo cuando ya no queda ninguno
+fn status_without_fault(current: &SystemStatus, fault: SystemFault) -> Option<SystemStatus> {
+    match current {
+        SystemStatus::Fault(faults) if faults.contains(&fault) => {
+            let remaining: Vec<SystemFault> = faults.iter().copied().filter(|f| *f != fault).collect();
+            Some(if remaining.is_empty() { SystemStatus::Active } else { SystemStatus::Fault(remaining) })
+        }
+        _ => None,
+    }
+}
+
 // 🚀 Entry point
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -259,18 +517,88 @@ mod tests {
     use super::*;
     use tokio_test;
 
+    // Calibración del IMU en un directorio temporal: los tests nunca tocan `config/`
+    fn test_calibration(name: &str) -> ImuCalibrationConfig {
+        ImuCalibrationConfig {
+            file_path: std::env::temp_dir().join(format!("mechros2_{}_imu_cal_{}.json", name, std::process::id())),
+            ..ImuCalibrationConfig::default()
+        }
+    }
+
+    async fn test_hub() -> MechROS2Hub {
+        MechROS2Hub::with_imu_calibration(test_calibration("hub")).await.expect("Failed to create hub")
+    }
+
     #[tokio::test]
     async fn test_system_initialization() {
-        let hub = MechROS2Hub::new().await.expect("Failed to create hub");
+        let hub = test_hub().await;
         assert!(hub.initialize().await.is_ok());
     }
 
     #[tokio::test]
     async fn test_system_state_update() {
-        let hub = MechROS2Hub::new().await.expect("Failed to create hub");
+        let hub = test_hub().await;
         hub.initialize().await.expect("Failed to initialize");
 
         let state = hub.system_state.read().await;
         assert!(matches!(state.system_status, SystemStatus::Ready));
     }
-      }
+
+    #[tokio::test]
+    async fn test_emergency_reset_after_incident() {
+        let hub = test_hub().await;
+        hub.initialize().await.expect("Failed to initialize");
+
+        hub.actuator_controller.emergency_stop().await.expect("Emergency stop failed");
+        assert!(hub.actuator_controller.get_status().await.emergency_stop_active);
+
+        hub.actuator_controller.reset_emergency_stop().await;
+        assert!(!hub.actuator_controller.get_status().await.emergency_stop_active);
+
+        // El rearme solo levanta el incidente; otros fallos simultáneos se mantienen
+        let incident = status_with_fault(&SystemStatus::Active, SystemFault::Incident).unwrap();
+        assert!(matches!(status_without_fault(&incident, SystemFault::Incident), Some(SystemStatus::Active)));
+        let with_environment = status_with_fault(&incident, SystemFault::Environment).unwrap();
+        let after_reset = status_without_fault(&with_environment, SystemFault::Incident).unwrap();
+        assert!(matches!(&after_reset, SystemStatus::Fault(faults) if faults == &[SystemFault::Environment]));
+        assert!(status_without_fault(&SystemStatus::Fault(vec![SystemFault::Environment]), SystemFault::Incident).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_imu_calibration_persisted_only_on_request() {
+        let calibration = test_calibration("operator");
+        let hub = MechROS2Hub::with_imu_calibration(calibration.clone()).await.expect("Failed to create hub");
+        hub.initialize().await.expect("Failed to initialize");
+        assert!(!calibration.file_path.exists());
+
+        hub.sensor_hub.calibrate_imu(ImuCalibrationStep::Gyro).await.expect("Gyro calibration failed");
+        assert!(calibration.file_path.exists());
+        std::fs::remove_file(&calibration.file_path).ok();
+    }
+
+    #[test]
+    fn test_environment_fault_from_battery_states() {
+        // La parada segura también se aplica con batería baja o crítica
+        for current in [SystemStatus::Active, SystemStatus::LowBattery(20.0), SystemStatus::CriticalBattery(5.0)] {
+            let faulted = status_with_fault(&current, SystemFault::Environment).expect("Safe stop ignored");
+            assert!(matches!(&faulted, SystemStatus::Fault(faults) if faults == &[SystemFault::Environment]));
+            assert!(status_with_fault(&faulted, SystemFault::Environment).is_none());
+            assert!(matches!(status_without_fault(&faulted, SystemFault::Environment), Some(SystemStatus::Active)));
+        }
+
+        assert!(status_with_fault(&SystemStatus::Shutdown, SystemFault::Environment).is_none());
+        assert!(status_without_fault(&SystemStatus::Active, SystemFault::Environment).is_none());
+    }
+
+    #[test]
+    fn test_imu_failure_alongside_environment_fault() {
+        let imu_down = status_with_fault(&SystemStatus::LowBattery(18.0), SystemFault::ImuFailure).expect("IMU failure ignored");
+        let both = status_with_fault(&imu_down, SystemFault::Environment).unwrap();
+        assert!(matches!(&both, SystemStatus::Fault(faults) if faults.len() == 2));
+
+        // Recuperar el IMU no levanta la parada ambiental
+        let environment_only = status_without_fault(&both, SystemFault::ImuFailure).unwrap();
+        assert!(matches!(&environment_only, SystemStatus::Fault(faults) if faults == &[SystemFault::Environment]));
+        assert!(matches!(status_without_fault(&environment_only, SystemFault::Environment), Some(SystemStatus::Active)));
+    }
+}
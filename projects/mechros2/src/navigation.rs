@@ -2,17 +2,41 @@
 // File: projects/mechros2/src/navigation.rs
 
 use std::sync::Arc;
-use std::collections::VecDeque;
-use nalgebra::{Vector3, Point3, distance};
+use std::collections::{HashMap, VecDeque};
+use nalgebra::{Vector3, Point3, Isometry3, distance};
 use serde::{Deserialize, Serialize};
 use tracing::{info, debug, warn, error};
-use crate::{SystemState, node_manager::MechNodeManager};
+use crate::{SystemState, SystemStatus, node_manager::MechNodeManager};
 use crate::vision::VisionData;
+use crate::sensors::LidarData;
+use crate::sensors::orientation::{wrap_angle, RollPitchYaw};
+use crate::sensors::proximity::{ProximityReading, ProximitySensorKind};
+
+pub mod avoidance;
+pub mod dwa;
+pub mod grid_planner;
+pub mod hybrid_planner;
+pub mod lidar_obstacles;
+pub mod map_io;
+pub mod occupancy_grid;
+pub mod path_tracking;
+pub mod tracking;
+pub mod velocity_profile;
+
+use avoidance::{ObstacleAvoidance, PredictiveAvoidanceConfig};
+use dwa::{DwaConfig, DwaPlanner};
+use grid_planner::{GridPlanner, GridPlannerConfig};
+use hybrid_planner::{HybridPlanner, HybridPlannerConfig};
+use lidar_obstacles::LidarObstacleExtractor;
+use occupancy_grid::{OccupancyGrid, OccupancyGridConfig};
+use path_tracking::{PathTracker, PathTrackerConfig};
+use tracking::{ObstacleTracker, TrackerConfig};
+use velocity_profile::{VelocityProfileConfig, VelocityProfiler};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NavigationCommands {
     pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub linear_velocity: Vector3<f64>,
+    pub linear_velocity: Vector3<f64>,  // Marco del robot (x adelante, y izquierda)
     pub angular_velocity: Vector3<f64>,
     pub target_position: Option<Point3<f64>>,
     pub command_type: CommandType,
@@ -43,6 +67,10 @@ pub struct Waypoint {
     pub tolerance: f64,
     pub max_speed: f64,
     pub waypoint_type: WaypointType,
+    #[serde(default)]
+    pub planned_speed: f64, // Velocidad de paso prevista por el perfil (m/s)
+    #[serde(default)]
+    pub segment_time: f64,  // Tiempo estimado del tramo que termina aquí (s)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,21 +91,32 @@ pub struct Path {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObstacleMap {
-    pub obstacles: Vec<Obstacle>,
+    pub obstacles: Vec<Obstacle>, // Obstáculos transitorios (visión, proximidad, segmentos LiDAR)
     pub last_update: chrono::DateTime<chrono::Utc>,
     pub confidence: f32,
+    pub occupancy: OccupancyGrid, // Mapa persistente construido con el LiDAR
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Obstacle {
     pub position: Point3<f64>,
     pub size: Vector3<f64>,
+    #[serde(default)]
+    pub yaw: f64, // Giro de la caja `size` alrededor de Z (rad); 0 = alineada con el mundo
     pub velocity: Option<Vector3<f64>>,
     pub obstacle_type: ObstacleType,
     pub confidence: f32,
+    pub source: ObstacleSource,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ObstacleSource {
+    Vision,
+    Lidar,
+    Proximity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ObstacleType {
     Static,
     Dynamic,
@@ -91,11 +130,35 @@ pub struct NavigationPlanner {
     node_manager: Arc<MechNodeManager>,
     current_path: Option<Path>,
     waypoint_queue: VecDeque<Waypoint>,
-    obstacle_map: ObstacleMap,
+    obstacle_map: tokio::sync::RwLock<ObstacleMap>, // Compartido con las actualizaciones de sensores
     navigation_config: NavigationConfig,
     path_planner: PathPlanner,
     obstacle_avoidance: ObstacleAvoidance,
     pid_controller: PIDController,
+    lidar_extractor: LidarObstacleExtractor,
+    dwa_planner: DwaPlanner,
+    path_tracker: PathTracker,
+    vision_tracker: ObstacleTracker,
+    source_updates: tokio::sync::Mutex<HashMap<ObstacleSource, chrono::DateTime<chrono::Utc>>>,
+    battery_level: f32,
+    current_position: Point3<f64>,
+    current_yaw: f64,
+    last_map_publish: tokio::sync::Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+/// Control local que convierte el waypoint actual en velocidades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LocalPlanner {
+    Reactive, // PID hacia el waypoint + evasión por vector perpendicular
+    Dwa,      // Dynamic Window Approach sobre el mapa de obstáculos
+    PathTracking, // Pure pursuit / Stanley sobre la ruta completa + evasión
+}
+
+/// Planificador global que genera la ruta de waypoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GlobalPlanner {
+    Grid,        // A*/Theta* holonómico: el robot como un punto
+    HybridAStar, // Modelo cinemático, radio de giro y huella del robot
 }
 
 #[derive(Debug, Clone)]
@@ -106,6 +169,20 @@ pub struct NavigationConfig {
     pub orientation_tolerance: f64,
     pub obstacle_safety_distance: f64,
     pub planning_frequency: f64,
+    pub battery_reserve: f64, // % mínimo para aceptar nuevos objetivos
+    pub occupancy_grid: OccupancyGridConfig,
+    pub map_publish_interval: f64, // s entre publicaciones del mapa de ocupación
+    pub map_file: Option<String>,  // YAML de map_server cargado al iniciar
+    pub maps_directory: String,    // Único directorio accesible a load_map/save_map remotos
+    pub global_planner: GlobalPlanner,
+    pub hybrid: HybridPlannerConfig,
+    pub local_planner: LocalPlanner,
+    pub dwa: DwaConfig,
+    pub tracker: TrackerConfig,
+    pub obstacle_timeout: f64, // s sin datos de una fuente antes de descartar sus obstáculos
+    pub predictive: PredictiveAvoidanceConfig,
+    pub path_tracking: PathTrackerConfig,
+    pub velocity_profile: VelocityProfileConfig,
 }
 
 impl Default for NavigationConfig {
@@ -117,6 +194,20 @@ impl Default for NavigationConfig {
             orientation_tolerance: 0.1,
             obstacle_safety_distance: 0.5,
             planning_frequency: 10.0,
+            battery_reserve: 15.0,
+            occupancy_grid: OccupancyGridConfig::default(),
+            map_publish_interval: 1.0,
+            map_file: None,
+            maps_directory: "maps".to_string(),
+            global_planner: GlobalPlanner::Grid,
+            hybrid: HybridPlannerConfig::default(),
+            local_planner: LocalPlanner::Reactive,
+            dwa: DwaConfig::default(),
+            tracker: TrackerConfig::default(),
+            obstacle_timeout: 5.0,
+            predictive: PredictiveAvoidanceConfig::default(),
+            path_tracking: PathTrackerConfig::default(),
+            velocity_profile: VelocityProfileConfig::default(),
         }
     }
 }
@@ -129,20 +220,39 @@ impl NavigationPlanner {
         let path_planner = PathPlanner::new(&config);
         let obstacle_avoidance = ObstacleAvoidance::new(&config);
         let pid_controller = PIDController::new(1.0, 0.1, 0.05);
+        let dwa_planner = DwaPlanner::new(
+            config.dwa.clone(),
+            config.max_linear_speed,
+            config.max_angular_speed,
+            1.0 / config.planning_frequency,
+        );
+
+        let path_tracker = PathTracker::new(config.path_tracking.clone(), config.max_linear_speed, config.max_angular_speed);
+        let vision_tracker = ObstacleTracker::new(config.tracker.clone());
 
         let planner = Self {
             node_manager,
             current_path: None,
             waypoint_queue: VecDeque::new(),
-            obstacle_map: ObstacleMap {
+            obstacle_map: tokio::sync::RwLock::new(ObstacleMap {
                 obstacles: Vec::new(),
                 last_update: chrono::Utc::now(),
                 confidence: 0.0,
-            },
+                occupancy: OccupancyGrid::new(config.occupancy_grid.clone()),
+            }),
             navigation_config: config,
             path_planner,
             obstacle_avoidance,
             pid_controller,
+            lidar_extractor: LidarObstacleExtractor::default(),
+            dwa_planner,
+            path_tracker,
+            vision_tracker,
+            source_updates: tokio::sync::Mutex::new(HashMap::new()),
+            battery_level: 100.0,
+            current_position: Point3::origin(),
+            current_yaw: 0.0,
+            last_map_publish: tokio::sync::Mutex::new(None),
         };
 
         info!("✅ Navigation Planner inicializado");
@@ -163,11 +273,33 @@ impl NavigationPlanner {
     }
 
     pub async fn update_navigation(&mut self, current_state: &SystemState) -> Result<Option<NavigationCommands>, Box<dyn std::error::Error>> {
+        self.battery_level = current_state.battery_level;
+        self.current_position = current_state.position;
+        self.current_yaw = current_state.orientation.yaw();
+
+        // Con batería crítica se abandona la ruta actual
+        if let SystemStatus::CriticalBattery(level) = current_state.system_status {
+            if !self.waypoint_queue.is_empty() {
+                warn!("🪫 Batería crítica ({:.1}%), cancelando navegación", level);
+                self.waypoint_queue.clear();
+                self.current_path = None;
+                return Ok(Some(NavigationCommands {
+                    timestamp: chrono::Utc::now(),
+                    linear_velocity: Vector3::zeros(),
+                    angular_velocity: Vector3::zeros(),
+                    target_position: None,
+                    command_type: CommandType::Stop,
+                    priority: NavigationPriority::High,
+                }));
+            }
+        }
+
         // Actualizar mapa de obstáculos
         self.update_obstacle_map().await?;
 
         // Procesar nuevos objetivos desde ROS2
         self.process_navigation_goals().await?;
+        self.process_map_commands().await?;
 
         // Si no hay waypoints, no hacer nada
         if self.waypoint_queue.is_empty() {
@@ -197,39 +329,122 @@ impl NavigationPlanner {
             }
         }
 
-        // Planificar movimiento hacia el waypoint
-        let commands = self.plan_movement(current_state, &current_waypoint).await?;
+        let safe_commands = match self.navigation_config.local_planner {
+            LocalPlanner::Reactive => {
+                // Planificar movimiento hacia el waypoint
+                let commands = self.plan_movement(current_state, &current_waypoint).await?;
 
-        // Aplicar evasión de obstáculos
-        let safe_commands = self.obstacle_avoidance.adjust_commands(&commands, &self.obstacle_map).await?;
+                // Aplicar evasión de obstáculos
+                let obstacle_map = self.obstacle_map.read().await;
+                self.obstacle_avoidance.adjust_commands(&commands, &obstacle_map, &current_state.pose()).await?
+            }
+            LocalPlanner::Dwa => self.plan_dwa(current_state, &current_waypoint).await,
+            LocalPlanner::PathTracking => {
+                let commands = match self.plan_tracking(current_state) {
+                    Some(commands) => commands,
+                    None => self.plan_movement(current_state, &current_waypoint).await?,
+                };
+                let obstacle_map = self.obstacle_map.read().await;
+                self.obstacle_avoidance.adjust_commands(&commands, &obstacle_map, &current_state.pose()).await?
+            }
+        };
 
         debug!("🎮 Comandos de navegación generados: {:?}", safe_commands);
         Ok(Some(safe_commands))
     }
 
+    /// Asocia las detecciones de visión con los tracks existentes y sustituye los
+    /// obstáculos de visión por los tracks confirmados, con su velocidad estimada.
     pub async fn update_vision_data(&mut self, vision_data: VisionData) -> Result<(), Box<dyn std::error::Error>> {
-        // Actualizar mapa de obstáculos con datos de visión
-        for detected_object in &vision_data.detected_objects {
-            if let Some(position) = detected_object.world_position {
-                let obstacle = Obstacle {
-                    position,
-                    size: Vector3::new(0.5, 0.5, 1.0), // Tamaño estimado
-                    velocity: None,
-                    obstacle_type: match detected_object.class.as_str() {
-                        "person" => ObstacleType::Person,
-                        "car" | "vehicle" => ObstacleType::Vehicle,
-                        "wall" => ObstacleType::Wall,
-                        _ => ObstacleType::Unknown,
-                    },
-                    confidence: detected_object.confidence,
-                };
+        let detections: Vec<Obstacle> = vision_data.detected_objects.iter()
+            .filter_map(|detected_object| detected_object.world_position.map(|position| Obstacle {
+                position,
+                size: Vector3::new(0.5, 0.5, 1.0), // Tamaño estimado
+                yaw: 0.0,
+                velocity: None,
+                obstacle_type: match detected_object.class.as_str() {
+                    "person" => ObstacleType::Person,
+                    "car" | "vehicle" => ObstacleType::Vehicle,
+                    "wall" => ObstacleType::Wall,
+                    _ => ObstacleType::Unknown,
+                },
+                confidence: detected_object.confidence,
+                source: ObstacleSource::Vision,
+            }))
+            .collect();
+
+        let now = chrono::Utc::now();
+        self.vision_tracker.update(&detections, now);
+        let mut obstacle_map = self.obstacle_map.write().await;
+        refresh_tracked_obstacles(&mut obstacle_map, &self.vision_tracker);
+        obstacle_map.last_update = now;
+        self.source_updates.lock().await.insert(ObstacleSource::Vision, now);
+        debug!("👁️ {} detecciones, {} tracks activos, {} obstáculos en total",
+               detections.len(), self.vision_tracker.tracks().len(), obstacle_map.obstacles.len());
+
+        Ok(())
+    }
+
+    /// Integra el barrido en el mapa de ocupación y sustituye los obstáculos LiDAR
+    /// por los extraídos del barrido actual. `points` es la nube filtrada en el
+    /// marco del robot y `robot_pose` la pose del robot en el instante del barrido.
+    pub async fn update_lidar_data(&self, scan: &LidarData, points: &[Point3<f64>], robot_pose: &Isometry3<f64>) -> Result<(), Box<dyn std::error::Error>> {
+        let obstacles = self.lidar_extractor.extract(points, robot_pose);
+        let lidar_count = obstacles.len();
 
-                self.obstacle_map.obstacles.push(obstacle);
+        let mut obstacle_map = self.obstacle_map.write().await;
+        obstacle_map.occupancy.update_scan(scan, robot_pose);
+        obstacle_map.obstacles.retain(|o| o.source != ObstacleSource::Lidar);
+        obstacle_map.obstacles.extend(obstacles);
+
+        let now = chrono::Utc::now();
+        obstacle_map.last_update = now;
+        self.source_updates.lock().await.insert(ObstacleSource::Lidar, now);
+        debug!("📡 {} obstáculos LiDAR, {} en total", lidar_count, obstacle_map.obstacles.len());
+
+        let publish_due = {
+            let mut last_publish = self.last_map_publish.lock().await;
+            let due = last_publish.is_none_or(|last| {
+                (now - last).num_milliseconds() as f64 / 1000.0 >= self.navigation_config.map_publish_interval
+            });
+            if due {
+                *last_publish = Some(now);
             }
+            due
+        };
+        if publish_due {
+            // Publicar sin bloquear la navegación mientras se serializa el mapa
+            let obstacle_map = obstacle_map.downgrade();
+            self.node_manager.publish_occupancy_grid(&obstacle_map.occupancy).await?;
         }
 
-        self.obstacle_map.last_update = chrono::Utc::now();
-        debug!("👁️ Mapa de obstáculos actualizado con {} obstáculos", self.obstacle_map.obstacles.len());
+        Ok(())
+    }
+
+    /// Sustituye los obstáculos de proximidad por las detecciones actuales.
+    pub async fn update_proximity_data(&self, readings: &[ProximityReading], current_state: &SystemState) -> Result<(), Box<dyn std::error::Error>> {
+        let pose = current_state.pose();
+
+        let mut obstacle_map = self.obstacle_map.write().await;
+        obstacle_map.obstacles.retain(|o| o.source != ObstacleSource::Proximity);
+        for reading in readings {
+            if let Some(point) = reading.detection {
+                obstacle_map.obstacles.push(Obstacle {
+                    position: pose * point,
+                    size: Vector3::new(reading.footprint.max(0.05), reading.footprint.max(0.05), 0.3),
+                    yaw: 0.0,
+                    velocity: None,
+                    obstacle_type: ObstacleType::Unknown,
+                    confidence: match reading.kind {
+                        ProximitySensorKind::Ultrasonic => 0.6,
+                        ProximitySensorKind::Infrared => 0.8,
+                        ProximitySensorKind::TimeOfFlight => 0.9,
+                    },
+                    source: ObstacleSource::Proximity,
+                });
+            }
+        }
+        self.source_updates.lock().await.insert(ObstacleSource::Proximity, chrono::Utc::now());
 
         Ok(())
     }
@@ -237,19 +452,31 @@ impl NavigationPlanner {
     pub async fn set_navigation_goal(&mut self, target: Point3<f64>) -> Result<(), Box<dyn std::error::Error>> {
         info!("🎯 Nuevo objetivo de navegación: {:?}", target);
 
+        if (self.battery_level as f64) < self.navigation_config.battery_reserve {
+            return Err(format!("Batería insuficiente para nuevos objetivos: {:.1}% < {:.1}%",
+                               self.battery_level, self.navigation_config.battery_reserve).into());
+        }
+
         // Limpiar waypoints anteriores
         self.waypoint_queue.clear();
 
         // Planificar ruta al objetivo
-        let path = self.path_planner.plan_path(&target, &self.obstacle_map).await?;
+        let path = {
+            let obstacle_map = self.obstacle_map.read().await;
+            self.path_planner.plan_path(&self.current_position, self.current_yaw, &target, &obstacle_map).await?
+        };
+
+        let positions: Vec<Point3<f64>> = path.waypoints.iter().map(|w| w.position).collect();
+        self.path_tracker.set_path(&self.current_position, &positions);
 
         // Agregar waypoints a la cola
         for waypoint in path.waypoints {
             self.waypoint_queue.push_back(waypoint);
         }
 
+        info!("🗺️ Ruta planificada con {} waypoints: {:.2}m, ~{:.1}s",
+              self.waypoint_queue.len(), path.total_distance, path.estimated_time);
         self.current_path = Some(path);
-        info!("🗺️ Ruta planificada con {} waypoints", self.waypoint_queue.len());
 
         Ok(())
     }
@@ -260,7 +487,7 @@ impl NavigationPlanner {
 
         // Control PID para velocidad
         let speed_command = self.pid_controller.update(distance_to_target, 0.0);
-        let max_speed = waypoint.max_speed.min(self.navigation_config.max_linear_speed);
+        let max_speed = self.profiled_speed(&current_state.position, waypoint);
         let clamped_speed = speed_command.abs().min(max_speed);
 
         // Calcular dirección
@@ -270,14 +497,19 @@ impl NavigationPlanner {
             Vector3::zeros()
         };
 
-        let linear_velocity = direction * clamped_speed;
-
         // Calcular velocidad angular (orientación hacia el objetivo)
         let target_yaw = target_vector.y.atan2(target_vector.x);
-        let current_yaw = current_state.orientation.z; // Asumiendo que Z es yaw
-        let yaw_error = self.normalize_angle(target_yaw - current_yaw);
+        let current_yaw = current_state.orientation.yaw();
+        let yaw_error = wrap_angle(target_yaw - current_yaw);
         let angular_velocity = Vector3::new(0.0, 0.0, yaw_error * 2.0); // Ganancia simple
 
+        // Velocidad en el marco del robot, como la consumen los actuadores. Con la
+        // ruta cinemática no hay desplazamiento lateral: solo avance según el rumbo
+        let linear_velocity = match self.navigation_config.global_planner {
+            GlobalPlanner::HybridAStar => Vector3::new(clamped_speed * yaw_error.cos().max(0.0), 0.0, 0.0),
+            GlobalPlanner::Grid => current_state.orientation.inverse_transform_vector(&(direction * clamped_speed)),
+        };
+
         Ok(NavigationCommands {
             timestamp: chrono::Utc::now(),
             linear_velocity,
@@ -292,19 +524,94 @@ impl NavigationPlanner {
         })
     }
 
-    fn reached_waypoint(&self, current_pos: &Point3<f64>, waypoint: &Waypoint) -> bool {
-        distance(current_pos, &waypoint.position) < waypoint.tolerance
+    /// Sigue la ruta completa; los waypoints que el controlador ya ha superado
+    /// (p. ej. al recortar una esquina) se retiran de la cola.
+    fn plan_tracking(&mut self, current_state: &SystemState) -> Option<NavigationCommands> {
+        let final_waypoint = self.waypoint_queue.back()?;
+        let stop_at_end = matches!(final_waypoint.waypoint_type, WaypointType::Stop | WaypointType::Precision);
+        let max_speed = self.profiled_speed(&current_state.position, self.waypoint_queue.front()?);
+
+        let tracking = self.path_tracker.compute(
+            &current_state.position,
+            current_state.orientation.yaw(),
+            current_state.velocity.xy().norm(),
+            max_speed,
+            stop_at_end,
+        )?;
+
+        while self.waypoint_queue.len() > self.path_tracker.remaining_waypoints().max(1) {
+            self.waypoint_queue.pop_front();
+        }
+        let waypoint = self.waypoint_queue.front()?;
+
+        Some(NavigationCommands {
+            timestamp: chrono::Utc::now(),
+            linear_velocity: Vector3::new(tracking.linear, 0.0, 0.0),
+            angular_velocity: Vector3::new(0.0, 0.0, tracking.angular),
+            target_position: Some(waypoint.position),
+            command_type: match waypoint.waypoint_type {
+                WaypointType::Stop => CommandType::Stop,
+                WaypointType::Precision => CommandType::Precision,
+                _ => CommandType::Move,
+            },
+            priority: NavigationPriority::Normal,
+        })
     }
 
-    fn normalize_angle(&self, angle: f64) -> f64 {
-        let mut normalized = angle;
-        while normalized > std::f64::consts::PI {
-            normalized -= 2.0 * std::f64::consts::PI;
-        }
-        while normalized < -std::f64::consts::PI {
-            normalized += 2.0 * std::f64::consts::PI;
+    /// Velocidades en el marco del robot (avance en x, giro en z) elegidas por DWA.
+    async fn plan_dwa(&mut self, current_state: &SystemState, waypoint: &Waypoint) -> NavigationCommands {
+        let position = current_state.position;
+        let horizon = self.navigation_config.max_linear_speed * self.dwa_planner.config().sim_time
+            + self.dwa_planner.config().robot_radius;
+        let obstacles = {
+            let obstacle_map = self.obstacle_map.read().await;
+            let mut obstacles = obstacle_map.occupancy.obstacles_near(&position, horizon);
+            obstacles.extend(obstacle_map.obstacles.iter().cloned());
+            obstacles
+        };
+
+        let max_speed = self.profiled_speed(&position, waypoint);
+
+        let yaw = current_state.orientation.yaw();
+        match self.dwa_planner.plan(&position, yaw, &waypoint.position, max_speed, &obstacles) {
+            Some(velocity) => NavigationCommands {
+                timestamp: chrono::Utc::now(),
+                linear_velocity: Vector3::new(velocity.linear, 0.0, 0.0),
+                angular_velocity: Vector3::new(0.0, 0.0, velocity.angular),
+                target_position: Some(waypoint.position),
+                command_type: match waypoint.waypoint_type {
+                    WaypointType::Stop => CommandType::Stop,
+                    WaypointType::Precision => CommandType::Precision,
+                    _ => CommandType::Move,
+                },
+                priority: NavigationPriority::Normal,
+            },
+            None => {
+                warn!("🚧 DWA sin trayectorias libres, deteniendo el robot");
+                NavigationCommands {
+                    timestamp: chrono::Utc::now(),
+                    linear_velocity: Vector3::zeros(),
+                    angular_velocity: Vector3::zeros(),
+                    target_position: Some(waypoint.position),
+                    command_type: CommandType::Hold,
+                    priority: NavigationPriority::High,
+                }
+            }
         }
-        normalized
+    }
+
+    /// Velocidad máxima que permite llegar al waypoint a su velocidad planificada
+    /// frenando con la aceleración del perfil.
+    fn profiled_speed(&self, position: &Point3<f64>, waypoint: &Waypoint) -> f64 {
+        let accel = self.navigation_config.velocity_profile.max_linear_accel;
+        let remaining = distance(position, &waypoint.position);
+        waypoint.max_speed
+            .min(self.navigation_config.max_linear_speed)
+            .min((waypoint.planned_speed.powi(2) + 2.0 * accel * remaining).sqrt())
+    }
+
+    fn reached_waypoint(&self, current_pos: &Point3<f64>, waypoint: &Waypoint) -> bool {
+        distance(current_pos, &waypoint.position) < waypoint.tolerance
     }
 
     async fn process_navigation_goals(&mut self) -> Result<(), Box<dyn std::error::Error>> {
@@ -318,7 +625,9 @@ impl NavigationPlanner {
                     goal_data["z"].as_f64()
                 ) {
                     let target = Point3::new(x, y, z);
-                    self.set_navigation_goal(target).await?;
+                    if let Err(e) = self.set_navigation_goal(target).await {
+                        warn!("⚠️ Objetivo de navegación rechazado: {}", e);
+                    }
                 }
             }
         }
@@ -326,9 +635,60 @@ impl NavigationPlanner {
         Ok(())
     }
 
+    /// Comandos remotos de mapas: `{"command": "load_map" | "save_map", "path": "<mapa>.yaml"}`.
+    async fn process_map_commands(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let commands = self.node_manager.take_remote_commands(&["load_map", "save_map"]).await;
+
+        for command_str in commands {
+            let Ok(command) = serde_json::from_str::<serde_json::Value>(&command_str) else { continue };
+            let Some(requested) = command["path"].as_str() else {
+                warn!("⚠️ Comando de mapa sin ruta: {}", command_str);
+                continue;
+            };
+            // El tópico no está autenticado: solo rutas dentro del directorio de mapas
+            let maps_directory = std::path::Path::new(&self.navigation_config.maps_directory);
+            let path = match map_io::resolve_map_path(maps_directory, requested) {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("⚠️ Comando de mapa rechazado: {}", e);
+                    continue;
+                }
+            };
+            let result = match command["command"].as_str() {
+                Some("load_map") => self.load_map(&path).await,
+                Some("save_map") => self.save_map(&path).await,
+                _ => continue,
+            };
+            if let Err(e) = result {
+                warn!("⚠️ Comando de mapa fallido ({}): {}", path.display(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sustituye el mapa de ocupación por uno estático en formato `map_server`.
+    /// Los barridos LiDAR posteriores siguen actualizándolo.
+    pub async fn load_map(&self, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        let grid = map_io::load_map(path, &self.navigation_config.occupancy_grid)?;
+        info!("🗺️ Mapa cargado desde {} ({}x{} celdas, {:.3} m/celda)", path.display(), grid.width(), grid.height(), grid.resolution());
+        self.obstacle_map.write().await.occupancy = grid;
+        *self.last_map_publish.lock().await = None; // Publicar en el próximo barrido
+        Ok(())
+    }
+
+    /// Guarda el mapa de ocupación actual en formato `map_server` (YAML + PGM).
+    pub async fn save_map(&self, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        map_io::save_map(&self.obstacle_map.read().await.occupancy, path)?;
+        info!("💾 Mapa guardado en {}", path.display());
+        Ok(())
+    }
+
     async fn load_maps(&self) -> Result<(), Box<dyn std::error::Error>> {
         debug!("🗺️ Cargando mapas de navegación...");
-        // Implementar carga de mapas desde archivos o servicios
+        if let Some(path) = self.navigation_config.map_file.clone() {
+            self.load_map(std::path::Path::new(&path)).await?;
+        }
         Ok(())
     }
 
@@ -339,47 +699,102 @@ impl NavigationPlanner {
     }
 
     async fn update_obstacle_map(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // Limpiar obstáculos antiguos (más de 5 segundos)
         let now = chrono::Utc::now();
-        let timeout = chrono::Duration::seconds(5);
 
-        if now - self.obstacle_map.last_update > timeout {
-            self.obstacle_map.obstacles.clear();
-            debug!("🧹 Mapa de obstáculos limpiado por timeout");
+        // Los tracks caducan uno a uno, aunque sigan llegando fotogramas
+        self.vision_tracker.prune(now);
+        let mut obstacle_map = self.obstacle_map.write().await;
+        refresh_tracked_obstacles(&mut obstacle_map, &self.vision_tracker);
+
+        // Fuentes que han dejado de informar
+        let timeout = self.navigation_config.obstacle_timeout;
+        let mut source_updates = self.source_updates.lock().await;
+        let stale: Vec<ObstacleSource> = source_updates.iter()
+            .filter(|(_, last)| (now - **last).num_milliseconds() as f64 / 1000.0 > timeout)
+            .map(|(source, _)| *source)
+            .collect();
+        for source in stale {
+            source_updates.remove(&source);
+            obstacle_map.obstacles.retain(|o| o.source != source);
+            debug!("🧹 Obstáculos de {:?} descartados por timeout", source);
         }
 
         Ok(())
     }
 }
 
+fn refresh_tracked_obstacles(obstacle_map: &mut ObstacleMap, tracker: &ObstacleTracker) {
+    obstacle_map.obstacles.retain(|o| o.source != ObstacleSource::Vision);
+    obstacle_map.obstacles.extend(tracker.obstacles());
+}
+
 // Planificador de rutas
 pub struct PathPlanner {
     config: NavigationConfig,
+    grid_planner: GridPlanner,
+    hybrid_planner: HybridPlanner,
+    profiler: VelocityProfiler,
 }
 
 impl PathPlanner {
     pub fn new(config: &NavigationConfig) -> Self {
-        Self { config: config.clone() }
+        let grid_config = GridPlannerConfig {
+            inflation_radius: config.obstacle_safety_distance,
+            ..GridPlannerConfig::default()
+        };
+        Self::with_grid_config(config, grid_config)
     }
 
-    pub async fn plan_path(&self, target: &Point3<f64>, obstacle_map: &ObstacleMap) -> Result<Path, Box<dyn std::error::Error>> {
-        debug!("🛤️ Planificando ruta hacia {:?}", target);
+    pub fn with_grid_config(config: &NavigationConfig, grid_config: GridPlannerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            grid_planner: GridPlanner::new(grid_config),
+            hybrid_planner: HybridPlanner::new(config.hybrid.clone()),
+            profiler: VelocityProfiler::new(
+                config.velocity_profile.clone(),
+                config.max_linear_speed,
+                config.max_angular_speed,
+            ),
+        }
+    }
 
-        // Algoritmo de planificación simple (A* simplificado)
-        let waypoints = vec![
-            Waypoint {
-                position: *target,
-                tolerance: self.config.position_tolerance,
-                max_speed: self.config.max_linear_speed,
-                waypoint_type: WaypointType::Stop,
+    /// Ruta A*/Theta* o Hybrid A* sobre la rejilla de ocupación; error si no existe ninguna.
+    pub async fn plan_path(&self, start: &Point3<f64>, start_yaw: f64, target: &Point3<f64>, obstacle_map: &ObstacleMap) -> Result<Path, Box<dyn std::error::Error>> {
+        debug!("🛤️ Planificando ruta de {:?} a {:?}", start, target);
+
+        let points = match self.config.global_planner {
+            GlobalPlanner::Grid => self.grid_planner.plan(start, target, &obstacle_map.obstacles, Some(&obstacle_map.occupancy))?,
+            GlobalPlanner::HybridAStar => {
+                let poses = self.hybrid_planner.plan(
+                    &start.xy(),
+                    start_yaw,
+                    &target.xy(),
+                    None,
+                    &obstacle_map.obstacles,
+                    Some(&obstacle_map.occupancy),
+                )?;
+                hybrid_planner::to_points(&poses, start, target)
             }
-        ];
+        };
+
+        // Los puntos intermedios solo se atraviesan; la tolerancia no baja de una celda
+        let transit_tolerance = self.config.position_tolerance.max(self.grid_planner.config().resolution);
+        let last = points.len() - 1;
+        let mut waypoints: Vec<Waypoint> = points.iter().enumerate().skip(1)
+            .map(|(i, position)| Waypoint {
+                position: *position,
+                tolerance: if i == last { self.config.position_tolerance } else { transit_tolerance },
+                max_speed: self.config.max_linear_speed,
+                waypoint_type: if i == last { WaypointType::Stop } else { WaypointType::Transit },
+                planned_speed: 0.0,
+                segment_time: 0.0,
+            })
+            .collect();
 
-        let total_distance = waypoints.iter()
-            .map(|wp| wp.position.coords.norm())
-            .sum();
+        let total_distance = grid_planner::path_length(&points);
 
-        let estimated_time = total_distance / self.config.max_linear_speed;
+        // Perfil de velocidad: aceleraciones, esquinas y paradas
+        let estimated_time = self.profiler.apply(start, &mut waypoints);
 
         Ok(Path {
             waypoints,
@@ -390,46 +805,6 @@ impl PathPlanner {
     }
 }
 
-// Sistema de evasión de obstáculos
-pub struct ObstacleAvoidance {
-    config: NavigationConfig,
-}
-
-impl ObstacleAvoidance {
-    pub fn new(config: &NavigationConfig) -> Self {
-        Self { config: config.clone() }
-    }
-
-    pub async fn adjust_commands(&self, commands: &NavigationCommands, obstacle_map: &ObstacleMap) -> Result<NavigationCommands, Box<dyn std::error::Error>> {
-        let mut adjusted_commands = commands.clone();
-
-        // Verificar colisiones potenciales
-        for obstacle in &obstacle_map.obstacles {
-            let distance = obstacle.position.coords.norm();
-
-            if distance < self.config.obstacle_safety_distance {
-                // Reducir velocidad cerca de obstáculos
-                let speed_factor = (distance / self.config.obstacle_safety_distance).max(0.1);
-                adjusted_commands.linear_velocity *= speed_factor;
-
-                // Aplicar vector de evasión lateral
-                let avoidance_vector = self.calculate_avoidance_vector(obstacle);
-                adjusted_commands.linear_velocity += avoidance_vector;
-
-                debug!("⚠️ Ajustando comandos por obstáculo a {:.2}m", distance);
-            }
-        }
-
-        Ok(adjusted_commands)
-    }
-
-    fn calculate_avoidance_vector(&self, obstacle: &Obstacle) -> Vector3<f64> {
-        // Vector simple de evasión perpendicular al obstáculo
-        let obstacle_dir = obstacle.position.coords.normalize();
-        Vector3::new(-obstacle_dir.y, obstacle_dir.x, 0.0) * 0.5
-    }
-}
-
 // Controlador PID simple
 pub struct PIDController {
     kp: f64,
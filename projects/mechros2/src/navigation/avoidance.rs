@@ -0,0 +1,346 @@
+// 🛡️ Reactive Obstacle Avoidance Module
+// File: projects/mechros2/src/navigation/avoidance.rs
+
+use nalgebra::{Isometry3, Point2, Rotation2, Vector2, Vector3};
+use tracing::debug;
+use super::{CommandType, NavigationCommands, NavigationConfig, NavigationPriority, Obstacle, ObstacleMap, ObstacleType};
+
+#[derive(Debug, Clone)]
+pub struct PredictiveAvoidanceConfig {
+    pub person_clearance: f64,    // Distancia mínima a personas (m)
+    pub vehicle_clearance: f64,
+    pub dynamic_clearance: f64,
+    pub time_horizon: f64,        // Colisiones previstas más allá de este tiempo se ignoran (s)
+    pub min_obstacle_speed: f64,  // Por debajo se trata el obstáculo como estático (m/s)
+    pub crossing_angle: f64,      // Ángulo mínimo entre trayectorias para considerar un cruce (rad)
+    pub max_heading_deviation: f64,
+    pub heading_step: f64,
+    pub speed_steps: usize,
+}
+
+impl Default for PredictiveAvoidanceConfig {
+    fn default() -> Self {
+        Self {
+            person_clearance: 1.0,
+            vehicle_clearance: 1.5,
+            dynamic_clearance: 0.8,
+            time_horizon: 3.0,
+            min_obstacle_speed: 0.1,
+            crossing_angle: 30f64.to_radians(),
+            max_heading_deviation: 60f64.to_radians(),
+            heading_step: 15f64.to_radians(),
+            speed_steps: 4,
+        }
+    }
+}
+
+/// Obstáculo visto desde el robot: distancia a su contorno y dirección en el marco del robot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeObstacle {
+    pub surface_distance: f64,
+    pub direction: Vector2<f64>, // Unitaria, marco del robot (x adelante, y izquierda)
+}
+
+// Sistema de evasión de obstáculos
+pub struct ObstacleAvoidance {
+    config: NavigationConfig,
+}
+
+impl ObstacleAvoidance {
+    pub fn new(config: &NavigationConfig) -> Self {
+        Self { config: config.clone() }
+    }
+
+    /// Ajusta comandos expresados en el marco del robot según los obstáculos
+    /// (marco mundo) cercanos a `robot_pose`. Los obstáculos en movimiento se
+    /// evitan por tiempo hasta colisión; ante una persona que cruza, el robot cede el paso.
+    pub async fn adjust_commands(&self, commands: &NavigationCommands, obstacle_map: &ObstacleMap, robot_pose: &Isometry3<f64>) -> Result<NavigationCommands, Box<dyn std::error::Error>> {
+        let mut adjusted_commands = commands.clone();
+        let predictive = &self.config.predictive;
+
+        let moving: Vec<&Obstacle> = obstacle_map.obstacles.iter()
+            .filter(|o| o.velocity.is_some_and(|v| v.xy().norm() >= predictive.min_obstacle_speed))
+            .collect();
+        if !moving.is_empty() {
+            let velocity = (robot_pose.rotation * commands.linear_velocity).xy();
+
+            if let Some(person) = moving.iter().find(|o| self.must_yield(o, robot_pose, &velocity)) {
+                debug!("🚶 Cediendo el paso a persona en ({:.2}, {:.2})", person.position.x, person.position.y);
+                adjusted_commands.linear_velocity = Vector3::zeros();
+                adjusted_commands.angular_velocity = Vector3::zeros();
+                adjusted_commands.command_type = CommandType::Hold;
+                adjusted_commands.priority = NavigationPriority::High;
+                return Ok(adjusted_commands);
+            }
+
+            if !self.is_safe(&velocity, &moving, robot_pose) {
+                let safe = self.safe_velocity(&velocity, &moving, robot_pose);
+                debug!("🔮 Velocidad ajustada por obstáculos móviles: {:?} -> {:?}", velocity, safe);
+                adjusted_commands.linear_velocity = robot_pose.rotation.inverse_transform_vector(&Vector3::new(safe.x, safe.y, 0.0));
+            }
+        }
+
+        // Celdas ocupadas del mapa en el entorno inmediato del robot
+        let position = robot_pose.translation.vector.into();
+        let mapped = obstacle_map.occupancy.obstacles_near(&position, self.config.obstacle_safety_distance);
+
+        // Verificar colisiones potenciales
+        for obstacle in obstacle_map.obstacles.iter().chain(&mapped) {
+            let relative = relative_obstacle(obstacle, robot_pose);
+            let safety_distance = self.clearance_for(&obstacle.obstacle_type);
+
+            if relative.surface_distance < safety_distance {
+                // Reducir velocidad cerca de obstáculos
+                let speed_factor = (relative.surface_distance / safety_distance).max(0.1);
+                adjusted_commands.linear_velocity *= speed_factor;
+
+                // Aplicar vector de evasión lateral
+                let avoidance_vector = self.calculate_avoidance_vector(&relative);
+                adjusted_commands.linear_velocity += avoidance_vector;
+
+                debug!("⚠️ Ajustando comandos por obstáculo a {:.2}m", relative.surface_distance);
+            }
+        }
+
+        Ok(adjusted_commands)
+    }
+
+    /// Distancia mínima a mantener según el tipo de obstáculo.
+    pub fn clearance_for(&self, obstacle_type: &ObstacleType) -> f64 {
+        let predictive = &self.config.predictive;
+        match obstacle_type {
+            ObstacleType::Person => predictive.person_clearance,
+            ObstacleType::Vehicle => predictive.vehicle_clearance,
+            ObstacleType::Dynamic => predictive.dynamic_clearance,
+            _ => self.config.obstacle_safety_distance,
+        }
+    }
+
+    /// Tiempo hasta que el robot, a `velocity` (mundo), invade la distancia de seguridad del obstáculo.
+    fn obstacle_ttc(&self, obstacle: &Obstacle, robot_pose: &Isometry3<f64>, velocity: &Vector2<f64>) -> Option<f64> {
+        let relative_position = (obstacle.position.coords - robot_pose.translation.vector).xy();
+        let relative_velocity = velocity - obstacle.velocity.unwrap_or_default().xy();
+        let radius = 0.5 * obstacle.size.x.max(obstacle.size.y) + self.clearance_for(&obstacle.obstacle_type);
+        time_to_collision(&relative_position, &relative_velocity, radius)
+    }
+
+    fn is_safe(&self, velocity: &Vector2<f64>, moving: &[&Obstacle], robot_pose: &Isometry3<f64>) -> bool {
+        moving.iter().all(|o| {
+            self.obstacle_ttc(o, robot_pose, velocity).is_none_or(|ttc| ttc > self.config.predictive.time_horizon)
+        })
+    }
+
+    /// Persona cuya trayectoria cruza la del robot con colisión prevista dentro del horizonte.
+    fn must_yield(&self, obstacle: &Obstacle, robot_pose: &Isometry3<f64>, velocity: &Vector2<f64>) -> bool {
+        if obstacle.obstacle_type != ObstacleType::Person || velocity.norm() < 1e-6 {
+            return false;
+        }
+        let person_velocity = obstacle.velocity.unwrap_or_default().xy();
+        let sin_angle = velocity.normalize().perp(&person_velocity.normalize()).abs();
+        sin_angle >= self.config.predictive.crossing_angle.sin()
+            && self.obstacle_ttc(obstacle, robot_pose, velocity).is_some_and(|ttc| ttc <= self.config.predictive.time_horizon)
+    }
+
+    /// Velocidad fuera de los obstáculos de velocidad más próxima a la deseada,
+    /// probando desvíos de rumbo y reducciones de velocidad; en último caso, parar.
+    fn safe_velocity(&self, desired: &Vector2<f64>, moving: &[&Obstacle], robot_pose: &Isometry3<f64>) -> Vector2<f64> {
+        let predictive = &self.config.predictive;
+        let steps = (predictive.max_heading_deviation / predictive.heading_step).round() as i32;
+        let speed_steps = predictive.speed_steps.max(1);
+
+        let mut best: Option<(f64, Vector2<f64>)> = None;
+        for s in 0..=speed_steps {
+            let scale = 1.0 - s as f64 / speed_steps as f64;
+            for k in -steps..=steps {
+                let candidate = Rotation2::new(k as f64 * predictive.heading_step) * desired * scale;
+                if !self.is_safe(&candidate, moving, robot_pose) {
+                    continue;
+                }
+                let deviation = (candidate - desired).norm();
+                if best.is_none_or(|(best_deviation, _)| deviation < best_deviation) {
+                    best = Some((deviation, candidate));
+                }
+            }
+        }
+        best.map_or_else(Vector2::zeros, |(_, velocity)| velocity)
+    }
+
+    fn calculate_avoidance_vector(&self, relative: &RelativeObstacle) -> Vector3<f64> {
+        // Perpendicular a la dirección del obstáculo, hacia el lado contrario a él
+        let dir = relative.direction;
+        let away = if dir.y >= 0.0 { Vector2::new(dir.y, -dir.x) } else { Vector2::new(-dir.y, dir.x) };
+        Vector3::new(away.x, away.y, 0.0) * 0.5
+    }
+}
+
+/// Distancia del centro del robot a la caja del obstáculo (girada `yaw`) y
+/// dirección hacia su punto más cercano en el marco del robot. Dentro de la caja
+/// la dirección apunta a su centro.
+pub fn relative_obstacle(obstacle: &Obstacle, robot_pose: &Isometry3<f64>) -> RelativeObstacle {
+    let robot = Point2::from(robot_pose.translation.vector.xy());
+    let nearest = closest_point(obstacle, &robot);
+    let surface_distance = nalgebra::distance(&robot, &nearest);
+
+    let target = if surface_distance > 1e-9 { nearest } else { obstacle.position.xy() };
+    let offset = target - robot;
+    let local = robot_pose.rotation.inverse_transform_vector(&Vector3::new(offset.x, offset.y, 0.0)).xy();
+    let direction = if local.norm() > 1e-9 { local.normalize() } else { Vector2::x() };
+
+    RelativeObstacle { surface_distance, direction }
+}
+
+/// Punto de la caja del obstáculo más cercano a `point` en el plano XY del mundo.
+pub fn closest_point(obstacle: &Obstacle, point: &Point2<f64>) -> Point2<f64> {
+    let rotation = Rotation2::new(obstacle.yaw);
+    let half = obstacle.size.xy() / 2.0;
+    let local = rotation.inverse() * (point - obstacle.position.xy());
+    let clamped = local.zip_map(&half, |v, h| v.clamp(-h, h));
+    obstacle.position.xy() + rotation * clamped
+}
+
+/// Menor t ≥ 0 con |p - w·t| ≤ radio, siendo `p` la posición relativa del obstáculo
+/// y `w` la velocidad del robot relativa a él. 0 si ya está dentro; `None` si no se acercan.
+pub fn time_to_collision(relative_position: &Vector2<f64>, relative_velocity: &Vector2<f64>, radius: f64) -> Option<f64> {
+    let c = relative_position.norm_squared() - radius * radius;
+    if c <= 0.0 {
+        // Dentro de la distancia de seguridad solo cuenta si se sigue acercando
+        return (relative_position.dot(relative_velocity) > 0.0).then_some(0.0);
+    }
+    let a = relative_velocity.norm_squared();
+    let b = -2.0 * relative_position.dot(relative_velocity);
+    let discriminant = b * b - 4.0 * a * c;
+    if a < 1e-12 || discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    (t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Translation3, UnitQuaternion};
+    use super::super::occupancy_grid::{OccupancyGrid, OccupancyGridConfig};
+    use super::super::{CommandType, NavigationPriority, ObstacleSource, ObstacleType};
+
+    fn obstacle(x: f64, y: f64, size: f64) -> Obstacle {
+        Obstacle {
+            position: Point3::new(x, y, 0.0),
+            size: Vector3::new(size, size, 1.0),
+            yaw: 0.0,
+            velocity: None,
+            obstacle_type: ObstacleType::Static,
+            confidence: 1.0,
+            source: ObstacleSource::Vision,
+        }
+    }
+
+    fn map(obstacles: Vec<Obstacle>) -> ObstacleMap {
+        ObstacleMap {
+            obstacles,
+            last_update: chrono::Utc::now(),
+            confidence: 1.0,
+            occupancy: OccupancyGrid::new(OccupancyGridConfig { initial_size: 2.0, ..OccupancyGridConfig::default() }),
+        }
+    }
+
+    fn forward(speed: f64) -> NavigationCommands {
+        NavigationCommands {
+            timestamp: chrono::Utc::now(),
+            linear_velocity: Vector3::new(speed, 0.0, 0.0),
+            angular_velocity: Vector3::zeros(),
+            target_position: None,
+            command_type: CommandType::Move,
+            priority: NavigationPriority::Normal,
+        }
+    }
+
+    fn pose(x: f64, y: f64, yaw: f64) -> Isometry3<f64> {
+        Isometry3::from_parts(Translation3::new(x, y, 0.0), UnitQuaternion::from_euler_angles(0.0, 0.0, yaw))
+    }
+
+    #[test]
+    fn test_relative_obstacle_in_robot_frame() {
+        // Robot en (10, 5) mirando al norte del mapa (+y): el obstáculo en +y queda delante
+        let robot = pose(10.0, 5.0, std::f64::consts::FRAC_PI_2);
+        let relative = relative_obstacle(&obstacle(10.0, 6.0, 0.4), &robot);
+        assert!((relative.surface_distance - 0.8).abs() < 1e-9);
+        assert!((relative.direction - Vector2::x()).norm() < 1e-9);
+
+        // Extensión: un obstáculo grande a la misma distancia queda mucho más cerca
+        let wide = relative_obstacle(&obstacle(10.0, 6.0, 1.6), &robot);
+        assert!((wide.surface_distance - 0.2).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_ignores_obstacles_near_map_origin() {
+        let avoidance = ObstacleAvoidance::new(&NavigationConfig::default());
+        let commands = forward(1.0);
+
+        // Cerca del origen del mapa pero lejos del robot: sin efecto
+        let adjusted = avoidance.adjust_commands(&commands, &map(vec![obstacle(0.1, 0.0, 0.2)]), &pose(-20.0, 15.0, 0.3)).await.unwrap();
+        assert_eq!(adjusted.linear_velocity, commands.linear_velocity);
+    }
+
+    fn moving(x: f64, y: f64, velocity: Vector3<f64>, obstacle_type: ObstacleType) -> Obstacle {
+        Obstacle { velocity: Some(velocity), obstacle_type, ..obstacle(x, y, 0.5) }
+    }
+
+    #[test]
+    fn test_time_to_collision() {
+        let ttc = time_to_collision(&Vector2::new(5.0, 0.0), &Vector2::new(1.0, 0.0), 1.0).unwrap();
+        assert!((ttc - 4.0).abs() < 1e-9);
+        assert!(time_to_collision(&Vector2::new(5.0, 0.0), &Vector2::new(-1.0, 0.0), 1.0).is_none());
+        assert!(time_to_collision(&Vector2::new(5.0, 3.0), &Vector2::new(1.0, 0.0), 1.0).is_none());
+        assert_eq!(time_to_collision(&Vector2::new(0.5, 0.0), &Vector2::new(1.0, 0.0), 1.0), Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_yields_to_crossing_person() {
+        let avoidance = ObstacleAvoidance::new(&NavigationConfig::default());
+        let robot = pose(10.0, 10.0, 0.0);
+
+        // Persona que cruza por delante de izquierda a derecha del mapa
+        let person = moving(12.0, 8.0, Vector3::new(0.0, 1.0, 0.0), ObstacleType::Person);
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![person.clone()]), &robot).await.unwrap();
+        assert_eq!(adjusted.linear_velocity, Vector3::zeros());
+        assert!(matches!(adjusted.command_type, CommandType::Hold));
+
+        // Un vehículo con la misma trayectoria no provoca parada sino desvío
+        let vehicle = Obstacle { obstacle_type: ObstacleType::Vehicle, ..person };
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![vehicle]), &robot).await.unwrap();
+        assert!(matches!(adjusted.command_type, CommandType::Move));
+    }
+
+    #[tokio::test]
+    async fn test_predicts_oncoming_vehicle_and_type_clearance() {
+        let avoidance = ObstacleAvoidance::new(&NavigationConfig::default());
+        let robot = pose(-5.0, 3.0, 0.0);
+
+        let vehicle = moving(1.0, 3.5, Vector3::new(-1.0, 0.0, 0.0), ObstacleType::Vehicle);
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![vehicle.clone()]), &robot).await.unwrap();
+        assert_ne!(adjusted.linear_velocity, Vector3::new(1.0, 0.0, 0.0));
+        let relative_position = (vehicle.position - Point3::new(-5.0, 3.0, 0.0)).xy();
+        let relative_velocity = adjusted.linear_velocity.xy() - vehicle.velocity.unwrap().xy();
+        assert!(time_to_collision(&relative_position, &relative_velocity, 0.25 + 1.5).is_none_or(|t| t > 3.0));
+
+        // A 0.8 m una persona parada frena al robot; un obstáculo estático no
+        let still_person = obstacle(-4.2, 3.0, 0.0);
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![still_person.clone()]), &robot).await.unwrap();
+        assert_eq!(adjusted.linear_velocity, Vector3::new(1.0, 0.0, 0.0));
+        let person = Obstacle { obstacle_type: ObstacleType::Person, ..still_person };
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![person]), &robot).await.unwrap();
+        assert!(adjusted.linear_velocity.x < 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_evades_away_from_obstacle_side() {
+        let avoidance = ObstacleAvoidance::new(&NavigationConfig::default());
+        let robot = pose(-7.0, 3.0, std::f64::consts::PI);
+
+        // Mirando a -x: un obstáculo en (-7.3, 2.8) queda delante y a la izquierda del robot
+        let adjusted = avoidance.adjust_commands(&forward(1.0), &map(vec![obstacle(-7.3, 2.8, 0.1)]), &robot).await.unwrap();
+        assert!(adjusted.linear_velocity.x < 1.0);
+        assert!(adjusted.linear_velocity.y < 0.0); // Se desvía hacia la derecha
+    }
+}
@@ -0,0 +1,310 @@
+// 🪟 Dynamic Window Approach Local Planner Module
+// File: projects/mechros2/src/navigation/dwa.rs
+
+use std::time::Instant;
+use nalgebra::{Point2, Point3};
+use super::Obstacle;
+use super::avoidance::closest_point;
+use crate::sensors::orientation::wrap_angle;
+
+#[derive(Debug, Clone)]
+pub struct DwaConfig {
+    pub max_linear_accel: f64,  // m/s²
+    pub max_angular_accel: f64, // rad/s²
+    pub min_linear_speed: f64,  // Negativo para permitir marcha atrás
+    pub sim_time: f64,          // Horizonte mínimo de simulación; se amplía hasta cubrir la frenada (s)
+    pub sim_step: f64,
+    pub linear_samples: usize,
+    pub angular_samples: usize,
+    pub heading_weight: f64,
+    pub clearance_weight: f64,
+    pub speed_weight: f64,
+    pub robot_radius: f64,
+    pub clearance_horizon: f64, // Holgura a partir de la cual no se puntúa más (m)
+    pub stale_after: f64,       // Sin planificar durante este tiempo se asume el robot parado (s)
+}
+
+impl Default for DwaConfig {
+    fn default() -> Self {
+        Self {
+            max_linear_accel: 1.0,
+            max_angular_accel: 3.0,
+            min_linear_speed: 0.0,
+            sim_time: 1.5,
+            sim_step: 0.1,
+            linear_samples: 11,
+            angular_samples: 21,
+            heading_weight: 0.8,
+            clearance_weight: 0.3,
+            speed_weight: 0.2,
+            robot_radius: 0.3,
+            clearance_horizon: 1.5,
+            stale_after: 0.5,
+        }
+    }
+}
+
+/// Velocidad de un robot diferencial en su propio marco: avance (m/s) y giro (rad/s).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DwaVelocity {
+    pub linear: f64,
+    pub angular: f64,
+}
+
+pub struct DwaPlanner {
+    config: DwaConfig,
+    max_linear_speed: f64,
+    max_angular_speed: f64,
+    control_period: f64,
+    velocity: DwaVelocity,
+    last_update: Option<Instant>,
+}
+
+impl DwaPlanner {
+    pub fn new(config: DwaConfig, max_linear_speed: f64, max_angular_speed: f64, control_period: f64) -> Self {
+        Self {
+            config,
+            max_linear_speed,
+            max_angular_speed,
+            control_period,
+            velocity: DwaVelocity::default(),
+            last_update: None,
+        }
+    }
+
+    pub fn config(&self) -> &DwaConfig {
+        &self.config
+    }
+
+    pub fn plan(&mut self, position: &Point3<f64>, yaw: f64, goal: &Point3<f64>, max_speed: f64, obstacles: &[Obstacle]) -> Option<DwaVelocity> {
+        self.plan_at(position, yaw, goal, max_speed, obstacles, Instant::now())
+    }
+
+    /// Elige la velocidad del siguiente ciclo partiendo de la última ordenada.
+    /// `None` si ninguna trayectoria alcanzable está libre: el robot debe detenerse.
+    pub fn plan_at(&mut self, position: &Point3<f64>, yaw: f64, goal: &Point3<f64>, max_speed: f64, obstacles: &[Obstacle], now: Instant) -> Option<DwaVelocity> {
+        let stale = self.last_update
+            .is_none_or(|last| now.duration_since(last).as_secs_f64() > self.config.stale_after);
+        let current = if stale { DwaVelocity::default() } else { self.velocity };
+        self.last_update = Some(now);
+
+        let best = self.select(current, position, yaw, goal, max_speed, obstacles);
+        self.velocity = best.unwrap_or_default();
+        best
+    }
+
+    /// Muestrea la ventana dinámica alrededor de `current` y devuelve el par (v, ω)
+    /// con mejor puntuación de orientación al objetivo, holgura y velocidad.
+    pub fn select(&self, current: DwaVelocity, position: &Point3<f64>, yaw: f64, goal: &Point3<f64>, max_speed: f64, obstacles: &[Obstacle]) -> Option<DwaVelocity> {
+        let cfg = &self.config;
+        let (v_lo, v_hi) = window(
+            current.linear,
+            cfg.max_linear_accel * self.control_period,
+            cfg.min_linear_speed,
+            max_speed.min(self.max_linear_speed),
+        );
+        let (w_lo, w_hi) = window(
+            current.angular,
+            cfg.max_angular_accel * self.control_period,
+            -self.max_angular_speed,
+            self.max_angular_speed,
+        );
+
+        let start = position.xy();
+        let goal = goal.xy();
+        let mut best: Option<(f64, DwaVelocity)> = None;
+        let initial_clearance = clearance(&[(start, yaw)], 0, obstacles, cfg.robot_radius, cfg.sim_step);
+
+        for v in samples(v_lo, v_hi, cfg.linear_samples) {
+            for w in samples(w_lo, w_hi, cfg.angular_samples) {
+                // Admisible solo si no hay colisión antes de poder detenerse. Si el robot
+                // ya está dentro del margen, se admite lo que no lo acerque más
+                let trajectory = self.simulate(&start, yaw, v, w);
+                let clearance = clearance(&trajectory[1..], 1, obstacles, cfg.robot_radius, cfg.sim_step);
+                let escaping = initial_clearance <= 0.0 && clearance >= initial_clearance - 1e-9;
+                if clearance <= 0.0 && !escaping {
+                    continue;
+                }
+                let clearance = clearance.max(0.0);
+
+                let (end, heading) = *trajectory.last()?;
+                let to_goal = goal - end;
+                let heading_error = wrap_angle(to_goal.y.atan2(to_goal.x) - heading).abs();
+
+                let score = cfg.heading_weight * (1.0 - heading_error / std::f64::consts::PI)
+                    + cfg.clearance_weight * clearance.min(cfg.clearance_horizon) / cfg.clearance_horizon
+                    + cfg.speed_weight * v / self.max_linear_speed;
+
+                if best.is_none_or(|(best_score, _)| score > best_score) {
+                    best = Some((score, DwaVelocity { linear: v, angular: w }));
+                }
+            }
+        }
+
+        best.map(|(_, velocity)| velocity)
+    }
+
+    /// Integra (v, ω) constantes durante el horizonte; incluye la pose inicial.
+    fn simulate(&self, start: &Point2<f64>, yaw: f64, v: f64, w: f64) -> Vec<(Point2<f64>, f64)> {
+        let horizon = self.config.sim_time.max(v.abs() / self.config.max_linear_accel);
+        let steps = (horizon / self.config.sim_step).ceil() as usize;
+        let mut pose = (*start, yaw);
+        let mut trajectory = Vec::with_capacity(steps + 1);
+        trajectory.push(pose);
+        for _ in 0..steps {
+            pose.1 += w * self.config.sim_step;
+            pose.0 += nalgebra::Vector2::new(pose.1.cos(), pose.1.sin()) * v * self.config.sim_step;
+            trajectory.push(pose);
+        }
+        trajectory
+    }
+}
+
+/// Ventana alcanzable en un ciclo, recortada a los límites. Si la velocidad actual
+/// ya excede el límite, la ventana se reduce a la máxima deceleración posible.
+fn window(current: f64, reach: f64, min: f64, max: f64) -> (f64, f64) {
+    let lo = (current - reach).max(min);
+    let hi = (current + reach).min(max);
+    if lo > hi {
+        let braking = (current - reach).max(min).min(current + reach);
+        (braking, braking)
+    } else {
+        (lo, hi)
+    }
+}
+
+fn samples(lo: f64, hi: f64, count: usize) -> impl Iterator<Item = f64> {
+    let count = count.max(1);
+    (0..count).map(move |i| if count == 1 { hi } else { lo + (hi - lo) * i as f64 / (count - 1) as f64 })
+}
+
+/// Holgura mínima entre el contorno del robot y la caja de cada obstáculo a lo largo
+/// de la trayectoria, desplazando los obstáculos móviles hasta el instante de cada
+/// punto. `first_step` es el índice temporal del primer punto.
+fn clearance(trajectory: &[(Point2<f64>, f64)], first_step: usize, obstacles: &[Obstacle], robot_radius: f64, step: f64) -> f64 {
+    trajectory.iter().enumerate()
+        .flat_map(|(k, (point, _))| obstacles.iter().map(move |obstacle| {
+            let elapsed = (first_step + k) as f64 * step;
+            let predicted = Obstacle {
+                position: obstacle.position + obstacle.velocity.unwrap_or_default() * elapsed,
+                ..obstacle.clone()
+            };
+            nalgebra::distance(point, &closest_point(&predicted, point)) - robot_radius
+        }))
+        .fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use super::super::{ObstacleSource, ObstacleType};
+
+    fn planner() -> DwaPlanner {
+        DwaPlanner::new(DwaConfig::default(), 1.0, 1.5, 0.1)
+    }
+
+    fn obstacle(x: f64, y: f64, size: f64) -> Obstacle {
+        Obstacle {
+            position: Point3::new(x, y, 0.0),
+            size: Vector3::new(size, size, 1.0),
+            yaw: 0.0,
+            velocity: None,
+            obstacle_type: ObstacleType::Static,
+            confidence: 1.0,
+            source: ObstacleSource::Lidar,
+        }
+    }
+
+    #[test]
+    fn test_respects_acceleration_limits() {
+        let mut planner = planner();
+        let start = Point3::new(5.0, -2.0, 0.0);
+        let goal = Point3::new(10.0, -2.0, 0.0);
+        let t0 = Instant::now();
+
+        let first = planner.plan_at(&start, 0.0, &goal, 1.0, &[], t0).unwrap();
+        assert!((first.linear - 0.1).abs() < 1e-9); // a·dt desde parado
+        assert!(first.angular.abs() < 1e-9);
+
+        let second = planner.plan_at(&start, 0.0, &goal, 1.0, &[], t0 + std::time::Duration::from_millis(100)).unwrap();
+        assert!((second.linear - 0.2).abs() < 1e-9);
+
+        // Tras un hueco largo se vuelve a partir de reposo
+        let third = planner.plan_at(&start, 0.0, &goal, 1.0, &[], t0 + std::time::Duration::from_secs(2)).unwrap();
+        assert!((third.linear - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_turns_away_from_obstacle_ahead() {
+        let planner = planner();
+        let start = Point3::new(-3.0, 4.0, 0.0);
+        let goal = Point3::new(2.0, 4.0, 0.0);
+        // Obstáculo por delante y a la izquierda: seguir recto o girar hacia él colisiona
+        let obstacles = [obstacle(-2.2, 4.35, 0.2)];
+        let current = DwaVelocity { linear: 0.8, angular: 0.0 };
+
+        let best = planner.select(current, &start, 0.0, &goal, 1.0, &obstacles).unwrap();
+        assert!(best.angular < 0.0);
+        assert!((best.linear - current.linear).abs() <= 0.1 + 1e-9);
+        assert!(clearance(&planner.simulate(&start.xy(), 0.0, best.linear, best.angular), 0, &obstacles, 0.3, 0.1) > 0.0);
+    }
+
+    #[test]
+    fn test_predicts_moving_obstacle() {
+        let planner = planner();
+        let start = Point3::new(2.0, 2.0, 0.0);
+        let goal = Point3::new(8.0, 2.0, 0.0);
+        let current = DwaVelocity { linear: 0.8, angular: 0.0 };
+
+        // Parado a 3 m no molesta; acercándose de frente a 1.5 m/s invade el horizonte
+        let mut oncoming = obstacle(5.0, 2.0, 0.4);
+        assert!(planner.select(current, &start, 0.0, &goal, 1.0, &[oncoming.clone()]).unwrap().angular.abs() < 1e-9);
+        oncoming.velocity = Some(Vector3::new(-1.5, 0.0, 0.0));
+        let best = planner.select(current, &start, 0.0, &goal, 1.0, &[oncoming]);
+        assert!(best.is_none_or(|v| v.angular.abs() > 1e-9 || v.linear < current.linear));
+    }
+
+    fn wall(x: f64, y: f64, length: f64, width: f64) -> Obstacle {
+        Obstacle { size: Vector3::new(length, width, 1.0), ..obstacle(x, y, 0.0) }
+    }
+
+    #[test]
+    fn test_long_wall_beside_robot_is_not_a_disc() {
+        let mut planner = planner();
+        let start = Point3::new(3.0, 1.0, 0.0);
+        let goal = Point3::new(8.0, 1.0, 0.0);
+        // Pared de 4 m paralela a la marcha, con su cara a 0.8 m del robot
+        let obstacles = [wall(3.0, 1.9, 4.0, 0.2)];
+
+        let velocity = planner.plan_at(&start, 0.0, &goal, 1.0, &obstacles, Instant::now()).unwrap();
+        assert!(velocity.linear > 0.0);
+    }
+
+    #[test]
+    fn test_escapes_when_inside_margin() {
+        let planner = planner();
+        let start = Point3::new(-2.0, 0.0, 0.0);
+        // Cara de la pared a 0.2 m: el radio de 0.3 m ya la invade
+        let obstacles = [wall(-1.7, 0.0, 0.2, 4.0)];
+        let goal = Point3::new(-5.0, 0.0, 0.0);
+
+        let best = planner.select(DwaVelocity::default(), &start, 0.0, &goal, 1.0, &obstacles).unwrap();
+        let trajectory = planner.simulate(&start.xy(), 0.0, best.linear, best.angular);
+        let initial = clearance(&trajectory[..1], 0, &obstacles, 0.3, 0.1);
+        assert!(initial < 0.0);
+        assert!(clearance(&trajectory, 0, &obstacles, 0.3, 0.1) >= initial - 1e-9);
+    }
+
+    #[test]
+    fn test_no_admissible_velocity_when_blocked() {
+        let planner = planner();
+        let start = Point3::new(1.0, 1.0, 0.0);
+        let goal = Point3::new(4.0, 1.0, 0.0);
+        // Fuera del margen, pero a 1 m/s no da tiempo a frenar ni a girar antes de la pared
+        let obstacles = [wall(2.1, 1.0, 0.2, 6.0)];
+        let current = DwaVelocity { linear: 1.0, angular: 0.0 };
+
+        assert!(planner.select(current, &start, 0.0, &goal, 1.0, &obstacles).is_none());
+    }
+}
@@ -0,0 +1,511 @@
+// 🧭 Grid Path Planning Module
+// File: projects/mechros2/src/navigation/grid_planner.rs
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use nalgebra::{Point2, Point3, Rotation2, Vector2};
+use super::Obstacle;
+use super::occupancy_grid::OccupancyGrid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    Euclidean,
+    /// Distancia exacta en una rejilla de 8 vecinos sin obstáculos.
+    Octile,
+    Manhattan,
+    /// Sin heurística: equivale a Dijkstra.
+    Zero,
+}
+
+impl Heuristic {
+    fn estimate(&self, dx: f64, dy: f64) -> f64 {
+        let (dx, dy) = (dx.abs(), dy.abs());
+        match self {
+            Heuristic::Euclidean => dx.hypot(dy),
+            Heuristic::Octile => dx.max(dy) + (std::f64::consts::SQRT_2 - 1.0) * dx.min(dy),
+            Heuristic::Manhattan => dx + dy,
+            Heuristic::Zero => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GridPlannerConfig {
+    pub resolution: f64, // m por celda
+    pub heuristic: Heuristic,
+    pub heuristic_weight: f64, // >1 acelera la búsqueda a costa de optimalidad
+    /// Theta*: los padres se eligen por línea de visión y la ruta sale con
+    /// ángulos arbitrarios; si no, A* sobre 8 vecinos.
+    pub any_angle: bool,
+    /// Margen alrededor de los obstáculos (radio del robot más holgura).
+    pub inflation_radius: f64,
+    /// Extensión de la rejilla más allá del rectángulo origen-destino (m).
+    pub search_margin: f64,
+    pub max_cells: usize,
+    pub min_obstacle_confidence: f32,
+}
+
+impl Default for GridPlannerConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 0.1,
+            heuristic: Heuristic::Euclidean,
+            heuristic_weight: 1.0,
+            any_angle: true,
+            inflation_radius: 0.5,
+            search_margin: 5.0,
+            max_cells: 1_000_000,
+            min_obstacle_confidence: 0.3,
+        }
+    }
+}
+
+/// Rejilla binaria de ocupación para planificar, en el plano XY del marco mundo.
+#[derive(Debug, Clone)]
+pub struct PlanningGrid {
+    origin: Point2<f64>, // Esquina inferior izquierda de la celda (0, 0)
+    resolution: f64,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    core: Vec<bool>, // Celdas que tocan el obstáculo en sí, sin el margen inflado
+}
+
+impl PlanningGrid {
+    pub fn new(origin: Point2<f64>, resolution: f64, width: usize, height: usize) -> Self {
+        Self {
+            origin,
+            resolution,
+            width,
+            height,
+            blocked: vec![false; width * height],
+            core: vec![false; width * height],
+        }
+    }
+
+    /// Rejilla que cubre origen y destino con margen, con los obstáculos y las
+    /// celdas ocupadas del mapa inflados. Lo desconocido se considera libre.
+    pub fn from_obstacles(
+        start: &Point2<f64>,
+        goal: &Point2<f64>,
+        obstacles: &[Obstacle],
+        occupancy: Option<&OccupancyGrid>,
+        config: &GridPlannerConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let min = start.coords.inf(&goal.coords).add_scalar(-config.search_margin);
+        let max = start.coords.sup(&goal.coords).add_scalar(config.search_margin);
+        let width = ((max.x - min.x) / config.resolution).ceil() as usize;
+        let height = ((max.y - min.y) / config.resolution).ceil() as usize;
+        if width * height > config.max_cells {
+            return Err(format!("Área de planificación demasiado grande: {}x{} celdas", width, height).into());
+        }
+
+        let mut grid = Self::new(Point2::from(min), config.resolution, width, height);
+        for obstacle in obstacles.iter().filter(|o| o.confidence >= config.min_obstacle_confidence) {
+            grid.block_box(&obstacle.position.xy(), &(obstacle.size.xy() / 2.0), obstacle.yaw, config.inflation_radius);
+        }
+        if let Some(occupancy) = occupancy {
+            let half = nalgebra::Vector2::repeat(occupancy.resolution() / 2.0);
+            let reach = nalgebra::Vector2::repeat(config.inflation_radius);
+            let (min, max) = (Point2::from(min) - reach, Point2::from(max) + reach);
+            for cell in occupancy.occupied_cells_in(&min, &max) {
+                let center = occupancy.cell_center(cell);
+                grid.block_rect(&(center - half), &(center + half), config.inflation_radius);
+            }
+        }
+        Ok(grid)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn cell_of(&self, point: &Point2<f64>) -> Option<(usize, usize)> {
+        let x = ((point.x - self.origin.x) / self.resolution).floor();
+        let y = ((point.y - self.origin.y) / self.resolution).floor();
+        (x >= 0.0 && y >= 0.0 && (x as usize) < self.width && (y as usize) < self.height)
+            .then_some((x as usize, y as usize))
+    }
+
+    pub fn cell_center(&self, cell: (usize, usize)) -> Point2<f64> {
+        Point2::new(
+            self.origin.x + (cell.0 as f64 + 0.5) * self.resolution,
+            self.origin.y + (cell.1 as f64 + 0.5) * self.resolution,
+        )
+    }
+
+    pub fn is_blocked(&self, cell: (usize, usize)) -> bool {
+        self.blocked[cell.1 * self.width + cell.0]
+    }
+
+    pub fn set_blocked(&mut self, cell: (usize, usize), blocked: bool) {
+        self.blocked[cell.1 * self.width + cell.0] = blocked;
+    }
+
+    /// Bloqueada por el obstáculo en sí y no solo por su margen de seguridad.
+    pub fn is_core(&self, cell: (usize, usize)) -> bool {
+        self.core[cell.1 * self.width + cell.0]
+    }
+
+    /// Celdas de margen inflado conectadas (8 vecinos) con `start`, sin atravesar
+    /// celdas de obstáculo. Incluye `start`; vacía si `start` está libre.
+    pub fn margin_region(&self, start: (usize, usize)) -> Vec<bool> {
+        let mut region = vec![false; self.width * self.height];
+        if !self.is_blocked(start) {
+            return region;
+        }
+        region[start.1 * self.width + start.0] = true;
+        let mut pending = vec![start];
+        while let Some(cell) = pending.pop() {
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (cell.0 as i64 + dx, cell.1 as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= self.width as i64 || ny >= self.height as i64 {
+                    continue;
+                }
+                let neighbor = (nx as usize, ny as usize);
+                let index = neighbor.1 * self.width + neighbor.0;
+                if !region[index] && self.is_blocked(neighbor) && !self.is_core(neighbor) {
+                    region[index] = true;
+                    pending.push(neighbor);
+                }
+            }
+        }
+        region
+    }
+
+    /// Bloquea las celdas cuyo centro queda a menos de `inflation` del rectángulo.
+    pub fn block_rect(&mut self, min: &Point2<f64>, max: &Point2<f64>, inflation: f64) {
+        self.block_box(&nalgebra::center(min, max), &((max - min) / 2.0), 0.0, inflation);
+    }
+
+    /// Igual que `block_rect` para una caja de semiejes `half` girada `yaw` rad.
+    pub fn block_box(&mut self, center: &Point2<f64>, half: &Vector2<f64>, yaw: f64, inflation: f64) {
+        let rotation = Rotation2::new(yaw);
+        let reach = rotation.matrix().abs() * half;
+        let lo = (center.coords - reach - self.origin.coords).add_scalar(-inflation) / self.resolution;
+        let hi = (center.coords + reach - self.origin.coords).add_scalar(inflation) / self.resolution;
+        let x_range = lo.x.floor().max(0.0) as usize..=(hi.x.ceil().max(0.0) as usize).min(self.width.saturating_sub(1));
+        let y_range = lo.y.floor().max(0.0) as usize..=(hi.y.ceil().max(0.0) as usize).min(self.height.saturating_sub(1));
+
+        for y in y_range {
+            for x in x_range.clone() {
+                let local = rotation.inverse() * (self.cell_center((x, y)) - center);
+                let outside = (local.abs() - half).sup(&Vector2::zeros()).norm();
+                let core = outside <= self.resolution / 2.0;
+                if outside <= inflation || core {
+                    self.set_blocked((x, y), true);
+                }
+                if core {
+                    self.core[y * self.width + x] = true;
+                }
+            }
+        }
+    }
+
+    /// Comprueba que el segmento entre dos centros de celda no cruza celdas bloqueadas.
+    fn line_of_sight(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (x0, y0) = (from.0 as f64, from.1 as f64);
+        let (dx, dy) = (to.0 as f64 - x0, to.1 as f64 - y0);
+        // Muestreo a un cuarto de celda: conservador frente a las esquinas
+        let steps = (dx.abs().max(dy.abs()) * 4.0).ceil() as usize;
+        (0..=steps).all(|i| {
+            let t = if steps == 0 { 0.0 } else { i as f64 / steps as f64 };
+            let cell = ((x0 + dx * t + 0.5).floor() as usize, (y0 + dy * t + 0.5).floor() as usize);
+            !self.is_blocked(cell)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct OpenNode {
+    pub(super) f: f64,
+    pub(super) g: f64,
+    pub(super) index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Montículo de mínimos por f; a igual f, preferir el nodo más avanzado
+        other.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
+            .then_with(|| self.g.partial_cmp(&other.g).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct GridPlanner {
+    config: GridPlannerConfig,
+}
+
+impl GridPlanner {
+    pub fn new(config: GridPlannerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &GridPlannerConfig {
+        &self.config
+    }
+
+    /// Ruta libre de colisiones entre `start` y `goal`, incluidos ambos extremos.
+    pub fn plan(
+        &self,
+        start: &Point3<f64>,
+        goal: &Point3<f64>,
+        obstacles: &[Obstacle],
+        occupancy: Option<&OccupancyGrid>,
+    ) -> Result<Vec<Point3<f64>>, Box<dyn std::error::Error>> {
+        let grid = PlanningGrid::from_obstacles(&start.xy(), &goal.xy(), obstacles, occupancy, &self.config)?;
+        let cells = self.search(&grid, &start.xy(), &goal.xy())?;
+        // Origen y objetivo en la misma celda: la búsqueda devuelve una sola, y el objetivo no se pierde
+        if cells.len() == 1 {
+            return Ok(vec![*start, *goal]);
+        }
+
+        let last = cells.len() - 1;
+        Ok(cells.iter().enumerate().map(|(i, &cell)| match i {
+            0 => *start,
+            i if i == last => *goal,
+            _ => {
+                let center = grid.cell_center(cell);
+                // Altura interpolada entre origen y destino
+                let t = i as f64 / last as f64;
+                Point3::new(center.x, center.y, start.z + (goal.z - start.z) * t)
+            }
+        }).collect())
+    }
+
+    /// Búsqueda A*/Theta* sobre la rejilla; devuelve las celdas de paso.
+    pub fn search(&self, grid: &PlanningGrid, start: &Point2<f64>, goal: &Point2<f64>) -> Result<Vec<(usize, usize)>, Box<dyn std::error::Error>> {
+        let start_cell = grid.cell_of(start).ok_or("Origen fuera de la rejilla de planificación")?;
+        let goal_cell = grid.cell_of(goal).ok_or("Objetivo fuera de la rejilla de planificación")?;
+        if grid.is_blocked(goal_cell) {
+            return Err(format!("Objetivo ({:.2}, {:.2}) dentro de un obstáculo o su margen de seguridad", goal.x, goal.y).into());
+        }
+
+        // El robot puede haber quedado dentro del margen inflado (junto a una pared o
+        // en la base de carga): ese margen se atraviesa con coste alto para salir,
+        // pero nunca las celdas del propio obstáculo
+        let escape = grid.margin_region(start_cell);
+
+        let width = grid.width();
+        let index = |cell: (usize, usize)| cell.1 * width + cell.0;
+        let cell_at = |index: usize| (index % width, index / width);
+        let passable = |cell: (usize, usize)| !grid.is_blocked(cell) || escape[index(cell)];
+        let heuristic = |cell: (usize, usize)| {
+            self.config.heuristic_weight * self.config.heuristic.estimate(
+                cell.0 as f64 - goal_cell.0 as f64,
+                cell.1 as f64 - goal_cell.1 as f64,
+            )
+        };
+
+        let size = grid.width() * grid.height();
+        let mut g_score = vec![f64::INFINITY; size];
+        let mut parent: Vec<Option<usize>> = vec![None; size];
+        let mut closed = vec![false; size];
+        let mut open = BinaryHeap::new();
+
+        g_score[index(start_cell)] = 0.0;
+        open.push(OpenNode { f: heuristic(start_cell), g: 0.0, index: index(start_cell) });
+
+        while let Some(OpenNode { g, index: current, .. }) = open.pop() {
+            if closed[current] || g > g_score[current] {
+                continue;
+            }
+            closed[current] = true;
+            let cell = cell_at(current);
+
+            if cell == goal_cell {
+                let mut path = vec![cell];
+                let mut node = current;
+                while let Some(p) = parent[node] {
+                    path.push(cell_at(p));
+                    node = p;
+                }
+                path.reverse();
+                return Ok(if self.config.any_angle { path } else { simplify(&path) });
+            }
+
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (cell.0 as i64 + dx, cell.1 as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= grid.width() as i64 || ny >= grid.height() as i64 {
+                    continue;
+                }
+                let neighbor = (nx as usize, ny as usize);
+                if !passable(neighbor) || closed[index(neighbor)] {
+                    continue;
+                }
+                // Sin cortar esquinas en diagonal
+                if dx != 0 && dy != 0 && (!passable((nx as usize, cell.1)) || !passable((cell.0, ny as usize))) {
+                    continue;
+                }
+
+                // Theta*: enlazar directamente con el abuelo si hay línea de visión
+                let (from, from_g) = match parent[current] {
+                    Some(p) if self.config.any_angle && grid.line_of_sight(cell_at(p), neighbor) => (p, g_score[p]),
+                    _ => (current, g),
+                };
+                let from_cell = cell_at(from);
+                let step = (neighbor.0 as f64 - from_cell.0 as f64).hypot(neighbor.1 as f64 - from_cell.1 as f64);
+                let n = index(neighbor);
+                let tentative = from_g + if escape[n] { step * ESCAPE_COST } else { step };
+                if tentative < g_score[n] {
+                    g_score[n] = tentative;
+                    parent[n] = Some(from);
+                    open.push(OpenNode { f: tentative + heuristic(neighbor), g: tentative, index: n });
+                }
+            }
+        }
+
+        Err(format!("No existe ruta libre de obstáculos hasta ({:.2}, {:.2})", goal.x, goal.y).into())
+    }
+}
+
+impl Default for GridPlanner {
+    fn default() -> Self {
+        Self::new(GridPlannerConfig::default())
+    }
+}
+
+/// Multiplicador del coste de las celdas de margen que se atraviesan para salir de él.
+const ESCAPE_COST: f64 = 10.0;
+
+const NEIGHBORS: [(i64, i64); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Elimina los puntos intermedios de tramos rectos.
+fn simplify(path: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+    let direction = |a: (usize, usize), b: (usize, usize)| (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64);
+
+    let mut simplified = vec![path[0]];
+    for window in path.windows(3) {
+        if direction(window[0], window[1]) != direction(window[1], window[2]) {
+            simplified.push(window[1]);
+        }
+    }
+    simplified.push(path[path.len() - 1]);
+    simplified
+}
+
+/// Longitud de una polilínea.
+pub fn path_length(points: &[Point3<f64>]) -> f64 {
+    points.windows(2).map(|w| nalgebra::distance(&w[0], &w[1])).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use super::super::{ObstacleSource, ObstacleType};
+
+    fn wall(center: Point3<f64>, size: Vector3<f64>) -> Obstacle {
+        Obstacle {
+            position: center,
+            size,
+            yaw: 0.0,
+            velocity: None,
+            obstacle_type: ObstacleType::Wall,
+            confidence: 1.0,
+            source: ObstacleSource::Lidar,
+        }
+    }
+
+    fn clearance(points: &[Point3<f64>], obstacle: &Obstacle) -> f64 {
+        // Distancia mínima de la ruta (muestreada) al rectángulo del obstáculo
+        let half = obstacle.size.xy() / 2.0;
+        points.windows(2).flat_map(|w| (0..=50).map(move |i| w[0] + (w[1] - w[0]) * (i as f64 / 50.0)))
+            .map(|p| {
+                let d = (p.xy() - obstacle.position.xy()).abs() - half;
+                d.sup(&nalgebra::Vector2::zeros()).norm()
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn test_open_space_is_straight_line() {
+        let planner = GridPlanner::default();
+        let start = Point3::new(0.0, 0.0, 0.0);
+        let goal = Point3::new(3.0, 4.0, 0.0);
+
+        let path = planner.plan(&start, &goal, &[], None).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!((path_length(&path) - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_goal_in_start_cell() {
+        let planner = GridPlanner::default();
+        let start = Point3::new(0.0, 0.0, 0.0);
+        let goal = Point3::new(0.02, 0.01, 0.0);
+
+        let path = planner.plan(&start, &goal, &[], None).unwrap();
+        assert_eq!(path, vec![start, goal]);
+    }
+
+    #[test]
+    fn test_path_goes_around_wall() {
+        let obstacle = wall(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.2, 4.0, 1.0));
+        let start = Point3::new(0.0, 0.0, 0.0);
+        let goal = Point3::new(4.0, 0.0, 0.0);
+
+        for any_angle in [true, false] {
+            let planner = GridPlanner::new(GridPlannerConfig { any_angle, ..GridPlannerConfig::default() });
+            let path = planner.plan(&start, &goal, std::slice::from_ref(&obstacle), None).unwrap();
+
+            // Rodea la pared respetando el margen (menos media celda de discretización)
+            assert!(clearance(&path, &obstacle) >= 0.5 - 0.1);
+            assert!(path_length(&path) > 4.0 + 2.0);
+            assert!(path_length(&path) < 10.0);
+        }
+    }
+
+    #[test]
+    fn test_start_inside_inflated_margin() {
+        // Robot a 0.3 m de una pared, dentro de su margen de 0.5 m
+        let obstacle = wall(Point3::new(0.4, 0.0, 0.0), Vector3::new(0.2, 4.0, 1.0));
+        let planner = GridPlanner::default();
+
+        // Alejándose de la pared
+        let away = planner.plan(&Point3::origin(), &Point3::new(-3.0, 0.0, 0.0), std::slice::from_ref(&obstacle), None).unwrap();
+        assert!((path_length(&away) - 3.0).abs() < 0.2);
+
+        // Al otro lado: sale del margen y la rodea, sin atravesarla
+        let across = planner.plan(&Point3::origin(), &Point3::new(1.5, 0.0, 0.0), std::slice::from_ref(&obstacle), None).unwrap();
+        assert!(path_length(&across) > 4.0);
+        assert!(clearance(&across[1..], &obstacle) > 0.0);
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        // Objetivo encerrado en una caja
+        let goal = Point3::new(5.0, 0.0, 0.0);
+        let walls = [
+            wall(Point3::new(4.0, 0.0, 0.0), Vector3::new(0.1, 3.0, 1.0)),
+            wall(Point3::new(6.0, 0.0, 0.0), Vector3::new(0.1, 3.0, 1.0)),
+            wall(Point3::new(5.0, 1.5, 0.0), Vector3::new(2.1, 0.1, 1.0)),
+            wall(Point3::new(5.0, -1.5, 0.0), Vector3::new(2.1, 0.1, 1.0)),
+        ];
+        let planner = GridPlanner::default();
+        let error = planner.plan(&Point3::origin(), &goal, &walls, None).unwrap_err();
+        assert!(error.to_string().contains("No existe ruta"));
+
+        let blocked_goal = Point3::new(4.0, 0.0, 0.0);
+        assert!(planner.plan(&Point3::origin(), &blocked_goal, &walls, None).is_err());
+    }
+}
@@ -0,0 +1,492 @@
+// 🚙 Hybrid A* Kinematic Planning Module
+// File: projects/mechros2/src/navigation/hybrid_planner.rs
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use nalgebra::{Point2, Point3, Vector2};
+use serde::{Deserialize, Serialize};
+use super::Obstacle;
+use super::grid_planner::{GridPlannerConfig, OpenNode, PlanningGrid};
+use super::occupancy_grid::OccupancyGrid;
+use crate::sensors::orientation::wrap_angle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VehicleModel {
+    DifferentialDrive, // Arcos con radio mínimo y giros sobre sí mismo
+    Ackermann,         // Solo arcos con radio mínimo
+}
+
+#[derive(Debug, Clone)]
+pub struct HybridPlannerConfig {
+    pub model: VehicleModel,
+    pub min_turning_radius: f64, // m
+    pub footprint_length: f64,   // Huella rectangular centrada en el eje de giro (m)
+    pub footprint_width: f64,
+    pub safety_margin: f64,      // Inflado de los obstáculos además de la huella (m)
+    pub resolution: f64,         // Celda de la rejilla de colisión y de estados (m)
+    pub heading_bins: usize,
+    pub step_length: f64,        // Longitud de arco de cada primitiva (m)
+    pub steering_samples: usize, // Curvaturas muestreadas en [-1/R, 1/R]
+    pub allow_reverse: bool,     // El seguidor de rutas actual solo avanza
+    pub reverse_penalty: f64,
+    pub direction_switch_penalty: f64, // m equivalentes por cambio de sentido
+    pub steering_penalty: f64,   // Coste extra relativo en curva a radio mínimo
+    pub rotation_penalty: f64,   // m equivalentes por radián girado en el sitio
+    pub goal_tolerance: f64,     // m
+    pub heading_tolerance: f64,  // rad, si se pide orientación final
+    pub analytic_interval: usize, // Expansiones entre intentos de conexión directa
+    pub search_margin: f64,
+    pub max_cells: usize,
+    pub max_expansions: usize,
+    pub min_obstacle_confidence: f32,
+}
+
+impl Default for HybridPlannerConfig {
+    fn default() -> Self {
+        Self {
+            model: VehicleModel::DifferentialDrive,
+            min_turning_radius: 0.8,
+            footprint_length: 0.6,
+            footprint_width: 0.5,
+            safety_margin: 0.1,
+            resolution: 0.1,
+            heading_bins: 72,
+            step_length: 0.4,
+            steering_samples: 5,
+            allow_reverse: false,
+            reverse_penalty: 2.0,
+            direction_switch_penalty: 1.0,
+            steering_penalty: 0.2,
+            rotation_penalty: 0.5,
+            goal_tolerance: 0.2,
+            heading_tolerance: 15f64.to_radians(),
+            analytic_interval: 10,
+            search_margin: 3.0,
+            max_cells: 1_000_000,
+            max_expansions: 200_000,
+            min_obstacle_confidence: 0.3,
+        }
+    }
+}
+
+/// Pose plana de la ruta; `reverse` indica que se llega a ella marcha atrás.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlannedPose {
+    pub position: Point2<f64>,
+    pub yaw: f64,
+    pub reverse: bool,
+}
+
+struct SearchNode {
+    pose: PlannedPose,
+    g: f64,
+    parent: Option<usize>,
+}
+
+/// Hybrid A*: búsqueda sobre (x, y, rumbo) con primitivas de movimiento
+/// cinemáticamente factibles y comprobación de la huella completa.
+pub struct HybridPlanner {
+    config: HybridPlannerConfig,
+    footprint: Vec<Vector2<f64>>, // Puntos de la huella en el marco del robot
+}
+
+impl HybridPlanner {
+    pub fn new(config: HybridPlannerConfig) -> Self {
+        let footprint = footprint_samples(config.footprint_length, config.footprint_width, config.resolution);
+        Self { config, footprint }
+    }
+
+    pub fn config(&self) -> &HybridPlannerConfig {
+        &self.config
+    }
+
+    /// Ruta desde la pose inicial hasta `goal`, incluida la pose de partida.
+    /// La última pose queda dentro de `goal_tolerance` del objetivo.
+    pub fn plan(
+        &self,
+        start: &Point2<f64>,
+        start_yaw: f64,
+        goal: &Point2<f64>,
+        goal_yaw: Option<f64>,
+        obstacles: &[Obstacle],
+        occupancy: Option<&OccupancyGrid>,
+    ) -> Result<Vec<PlannedPose>, Box<dyn std::error::Error>> {
+        let cfg = &self.config;
+        let grid_config = GridPlannerConfig {
+            resolution: cfg.resolution,
+            inflation_radius: cfg.safety_margin,
+            search_margin: cfg.search_margin,
+            max_cells: cfg.max_cells,
+            min_obstacle_confidence: cfg.min_obstacle_confidence,
+            ..GridPlannerConfig::default()
+        };
+        let grid = PlanningGrid::from_obstacles(start, goal, obstacles, occupancy, &grid_config)?;
+
+        let start_pose = PlannedPose { position: *start, yaw: start_yaw, reverse: false };
+        if self.collides(&grid, &start_pose) {
+            return Err(format!("Huella del robot en colisión en la pose inicial ({:.2}, {:.2})", start.x, start.y).into());
+        }
+        let goal_cell = grid.cell_of(goal).ok_or("Objetivo fuera de la rejilla de planificación")?;
+        if grid.is_blocked(goal_cell) {
+            return Err(format!("Objetivo ({:.2}, {:.2}) dentro de un obstáculo o su margen de seguridad", goal.x, goal.y).into());
+        }
+
+        // Heurística: distancia holonómica con obstáculos, nunca menor que la euclídea
+        let distances = holonomic_distances(&grid, goal_cell);
+        let heuristic = |pose: &PlannedPose| {
+            let euclidean = nalgebra::distance(&pose.position, goal);
+            grid.cell_of(&pose.position)
+                .map_or(f64::INFINITY, |cell| distances[cell.1 * grid.width() + cell.0])
+                .max(euclidean)
+        };
+        if !heuristic(&start_pose).is_finite() {
+            return Err(format!("No existe ruta libre de obstáculos hasta ({:.2}, {:.2})", goal.x, goal.y).into());
+        }
+
+        let mut nodes = vec![SearchNode { pose: start_pose, g: 0.0, parent: None }];
+        let mut best: HashMap<(usize, usize, usize), f64> = HashMap::new();
+        let mut closed: HashSet<(usize, usize, usize)> = HashSet::new();
+        let mut open = BinaryHeap::new();
+        open.push(OpenNode { f: heuristic(&start_pose), g: 0.0, index: 0 });
+
+        let mut expansions = 0;
+        while let Some(OpenNode { index: current, .. }) = open.pop() {
+            let pose = nodes[current].pose;
+            let Some(key) = self.state_key(&grid, &pose) else { continue };
+            if !closed.insert(key) {
+                continue;
+            }
+            expansions += 1;
+            if expansions > cfg.max_expansions {
+                return Err(format!("Hybrid A* sin solución tras {} expansiones", cfg.max_expansions).into());
+            }
+
+            let heading_ok = goal_yaw.is_none_or(|yaw| wrap_angle(yaw - pose.yaw).abs() <= cfg.heading_tolerance);
+            if nalgebra::distance(&pose.position, goal) <= cfg.goal_tolerance && heading_ok {
+                return Ok(simplify(self.reconstruct(&nodes, current)));
+            }
+
+            // Diferencial: girar en el sitio y avanzar en recta hasta el objetivo
+            if cfg.model == VehicleModel::DifferentialDrive && (expansions - 1) % cfg.analytic_interval.max(1) == 0 {
+                if let Some(tail) = self.direct_connection(&grid, &pose, goal, goal_yaw) {
+                    let mut path = self.reconstruct(&nodes, current);
+                    path.extend(tail);
+                    return Ok(simplify(path));
+                }
+            }
+
+            let g = nodes[current].g;
+            for (successor, cost) in self.successors(&grid, &pose, nodes[current].parent.is_some()) {
+                let Some(successor_key) = self.state_key(&grid, &successor) else { continue };
+                if closed.contains(&successor_key) {
+                    continue;
+                }
+                let tentative = g + cost;
+                if best.get(&successor_key).is_some_and(|&known| known <= tentative) {
+                    continue;
+                }
+                let h = heuristic(&successor);
+                if !h.is_finite() {
+                    continue;
+                }
+                best.insert(successor_key, tentative);
+                nodes.push(SearchNode { pose: successor, g: tentative, parent: Some(current) });
+                open.push(OpenNode { f: tentative + h, g: tentative, index: nodes.len() - 1 });
+            }
+        }
+
+        Err(format!("No existe ruta cinemáticamente factible hasta ({:.2}, {:.2})", goal.x, goal.y).into())
+    }
+
+    /// Arcos hacia delante (y atrás si se permite) y, en diferencial, giros en el sitio.
+    fn successors(&self, grid: &PlanningGrid, pose: &PlannedPose, has_parent: bool) -> Vec<(PlannedPose, f64)> {
+        let cfg = &self.config;
+        let max_curvature = 1.0 / cfg.min_turning_radius.max(1e-3);
+        let samples = cfg.steering_samples.max(1);
+        let curvatures = (0..samples).map(|i| {
+            if samples == 1 { 0.0 } else { -max_curvature + 2.0 * max_curvature * i as f64 / (samples - 1) as f64 }
+        });
+        let directions: &[bool] = if cfg.allow_reverse { &[false, true] } else { &[false] };
+
+        let mut successors = Vec::new();
+        for curvature in curvatures {
+            for &reverse in directions {
+                let substeps = (cfg.step_length / cfg.resolution).ceil().max(1.0) as usize;
+                let free = (1..=substeps).all(|i| {
+                    !self.collides(grid, &arc(pose, curvature, cfg.step_length * i as f64 / substeps as f64, reverse))
+                });
+                if !free {
+                    continue;
+                }
+                let mut cost = cfg.step_length * (1.0 + cfg.steering_penalty * curvature.abs() / max_curvature);
+                if reverse {
+                    cost *= cfg.reverse_penalty;
+                }
+                if has_parent && reverse != pose.reverse {
+                    cost += cfg.direction_switch_penalty;
+                }
+                successors.push((arc(pose, curvature, cfg.step_length, reverse), cost));
+            }
+        }
+
+        if cfg.model == VehicleModel::DifferentialDrive {
+            for angle in [std::f64::consts::FRAC_PI_4, -std::f64::consts::FRAC_PI_4] {
+                let yaw = pose.yaw + angle;
+                if self.rotation_free(grid, pose, yaw) {
+                    let rotated = PlannedPose { yaw, reverse: false, ..*pose };
+                    successors.push((rotated, cfg.rotation_penalty * angle.abs()));
+                }
+            }
+        }
+        successors
+    }
+
+    fn direct_connection(&self, grid: &PlanningGrid, from: &PlannedPose, goal: &Point2<f64>, goal_yaw: Option<f64>) -> Option<Vec<PlannedPose>> {
+        let delta = goal - from.position;
+        let length = delta.norm();
+        let bearing = if length > 1e-6 { delta.y.atan2(delta.x) } else { from.yaw };
+
+        let facing = PlannedPose { position: from.position, yaw: bearing, reverse: false };
+        if !self.rotation_free(grid, from, bearing) {
+            return None;
+        }
+        let steps = (length / self.config.resolution).ceil() as usize;
+        let blocked = (1..=steps).any(|i| {
+            let pose = PlannedPose { position: from.position + delta * (i as f64 / steps as f64), ..facing };
+            self.collides(grid, &pose)
+        });
+        if blocked {
+            return None;
+        }
+
+        let arrival = PlannedPose { position: *goal, ..facing };
+        let mut tail = vec![facing, arrival];
+        if let Some(yaw) = goal_yaw {
+            if !self.rotation_free(grid, &arrival, yaw) {
+                return None;
+            }
+            tail.push(PlannedPose { yaw, ..arrival });
+        }
+        Some(tail)
+    }
+
+    /// Comprueba la huella en los rumbos intermedios de un giro en el sitio.
+    fn rotation_free(&self, grid: &PlanningGrid, pose: &PlannedPose, target_yaw: f64) -> bool {
+        let delta = wrap_angle(target_yaw - pose.yaw);
+        let reach = 0.5 * self.config.footprint_length.hypot(self.config.footprint_width);
+        let steps = (delta.abs() * reach / self.config.resolution).ceil().max(1.0) as usize;
+        (1..=steps).all(|i| {
+            let yaw = pose.yaw + delta * i as f64 / steps as f64;
+            !self.collides(grid, &PlannedPose { yaw, ..*pose })
+        })
+    }
+
+    fn collides(&self, grid: &PlanningGrid, pose: &PlannedPose) -> bool {
+        let (sin, cos) = pose.yaw.sin_cos();
+        self.footprint.iter().any(|offset| {
+            let point = pose.position + Vector2::new(offset.x * cos - offset.y * sin, offset.x * sin + offset.y * cos);
+            grid.cell_of(&point).is_none_or(|cell| grid.is_blocked(cell))
+        })
+    }
+
+    fn state_key(&self, grid: &PlanningGrid, pose: &PlannedPose) -> Option<(usize, usize, usize)> {
+        let (x, y) = grid.cell_of(&pose.position)?;
+        let bins = self.config.heading_bins.max(1);
+        let bin = (pose.yaw.rem_euclid(2.0 * std::f64::consts::PI) / (2.0 * std::f64::consts::PI) * bins as f64).round() as usize % bins;
+        Some((x, y, bin))
+    }
+
+    fn reconstruct(&self, nodes: &[SearchNode], mut index: usize) -> Vec<PlannedPose> {
+        let mut path = vec![nodes[index].pose];
+        while let Some(parent) = nodes[index].parent {
+            path.push(nodes[parent].pose);
+            index = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl Default for HybridPlanner {
+    fn default() -> Self {
+        Self::new(HybridPlannerConfig::default())
+    }
+}
+
+/// Convierte la ruta en puntos 3D con los extremos exactos, como el planificador de rejilla.
+pub fn to_points(poses: &[PlannedPose], start: &Point3<f64>, goal: &Point3<f64>) -> Vec<Point3<f64>> {
+    // Objetivo ya alcanzado desde el origen: una sola pose, pero la ruta sigue terminando en el objetivo
+    if poses.len() <= 1 {
+        return vec![*start, *goal];
+    }
+    let last = poses.len() - 1;
+    poses.iter().enumerate().map(|(i, pose)| match i {
+        0 => *start,
+        i if i == last => *goal,
+        _ => {
+            let t = i as f64 / last as f64;
+            Point3::new(pose.position.x, pose.position.y, start.z + (goal.z - start.z) * t)
+        }
+    }).collect()
+}
+
+/// Integra un arco de curvatura constante recorriendo `length` metros.
+fn arc(pose: &PlannedPose, curvature: f64, length: f64, reverse: bool) -> PlannedPose {
+    let s = if reverse { -length } else { length };
+    let yaw = pose.yaw + curvature * s;
+    let delta = if curvature.abs() < 1e-9 {
+        Vector2::new(pose.yaw.cos(), pose.yaw.sin()) * s
+    } else {
+        Vector2::new(yaw.sin() - pose.yaw.sin(), pose.yaw.cos() - yaw.cos()) / curvature
+    };
+    PlannedPose { position: pose.position + delta, yaw, reverse }
+}
+
+/// Contorno e interior del rectángulo de la huella, con separación máxima `spacing`.
+fn footprint_samples(length: f64, width: f64, spacing: f64) -> Vec<Vector2<f64>> {
+    let nx = (length / spacing).ceil().max(1.0) as usize;
+    let ny = (width / spacing).ceil().max(1.0) as usize;
+    (0..=nx).flat_map(|i| (0..=ny).map(move |j| Vector2::new(
+        -length / 2.0 + length * i as f64 / nx as f64,
+        -width / 2.0 + width * j as f64 / ny as f64,
+    ))).collect()
+}
+
+/// Dijkstra de 8 vecinos desde el objetivo (m); infinito en celdas inalcanzables.
+fn holonomic_distances(grid: &PlanningGrid, goal: (usize, usize)) -> Vec<f64> {
+    let width = grid.width();
+    let mut distances = vec![f64::INFINITY; width * grid.height()];
+    let mut open = BinaryHeap::new();
+    distances[goal.1 * width + goal.0] = 0.0;
+    open.push(OpenNode { f: 0.0, g: 0.0, index: goal.1 * width + goal.0 });
+
+    while let Some(OpenNode { g, index, .. }) = open.pop() {
+        if g > distances[index] {
+            continue;
+        }
+        let (x, y) = (index % width, index / width);
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            if nx < 0 || ny < 0 || nx >= width as i64 || ny >= grid.height() as i64 {
+                continue;
+            }
+            let neighbor = (nx as usize, ny as usize);
+            if grid.is_blocked(neighbor) {
+                continue;
+            }
+            let n = neighbor.1 * width + neighbor.0;
+            let tentative = g + ((dx * dx + dy * dy) as f64).sqrt() * grid.resolution();
+            if tentative < distances[n] {
+                distances[n] = tentative;
+                open.push(OpenNode { f: tentative, g: tentative, index: n });
+            }
+        }
+    }
+    distances
+}
+
+/// Elimina los giros en el sitio intermedios (queda el rumbo final) y une tramos rectos.
+fn simplify(poses: Vec<PlannedPose>) -> Vec<PlannedPose> {
+    let mut simplified: Vec<PlannedPose> = Vec::with_capacity(poses.len());
+    for pose in poses {
+        if let Some(last) = simplified.last_mut() {
+            if nalgebra::distance(&last.position, &pose.position) < 1e-6 {
+                last.yaw = pose.yaw;
+                continue;
+            }
+        }
+        let n = simplified.len();
+        if n >= 2 {
+            let (a, b) = (simplified[n - 2], simplified[n - 1]);
+            let straight = wrap_angle(a.yaw - b.yaw).abs() < 1e-6 && wrap_angle(b.yaw - pose.yaw).abs() < 1e-6;
+            if straight && a.reverse == b.reverse && b.reverse == pose.reverse {
+                simplified[n - 1] = pose;
+                continue;
+            }
+        }
+        simplified.push(pose);
+    }
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use super::super::{ObstacleSource, ObstacleType};
+
+    fn wall(x: f64, y: f64, width: f64, height: f64) -> Obstacle {
+        Obstacle {
+            position: Point3::new(x, y, 0.0),
+            size: Vector3::new(width, height, 1.0),
+            yaw: 0.0,
+            velocity: None,
+            obstacle_type: ObstacleType::Wall,
+            confidence: 1.0,
+            source: ObstacleSource::Lidar,
+        }
+    }
+
+    #[test]
+    fn test_differential_drive_turns_in_place_and_drives_forward() {
+        let planner = HybridPlanner::default();
+        let start = Point2::new(4.0, -2.0);
+        let goal = Point2::new(1.0, -2.0); // Detrás del robot
+
+        let path = planner.plan(&start, 0.0, &goal, None, &[], None).unwrap();
+        assert_eq!(path[0].position, start);
+        assert!(nalgebra::distance(&path.last().unwrap().position, &goal) <= 0.2);
+        // Cada tramo se recorre hacia delante, en la dirección del rumbo de llegada
+        for pair in path.windows(2) {
+            let delta = pair[1].position - pair[0].position;
+            assert!(!pair[1].reverse);
+            assert!(delta.x * pair[1].yaw.cos() + delta.y * pair[1].yaw.sin() > 0.99 * delta.norm());
+        }
+    }
+
+    #[test]
+    fn test_single_pose_keeps_goal() {
+        let start = Point3::new(1.0, 1.0, 0.0);
+        let goal = Point3::new(1.05, 1.0, 0.0);
+        let poses = [PlannedPose { position: start.xy(), yaw: 0.0, reverse: false }];
+        assert_eq!(to_points(&poses, &start, &goal), vec![start, goal]);
+    }
+
+    #[test]
+    fn test_ackermann_respects_turning_radius() {
+        let config = HybridPlannerConfig { model: VehicleModel::Ackermann, ..HybridPlannerConfig::default() };
+        let planner = HybridPlanner::new(config);
+        let start = Point2::new(-1.0, 1.0);
+        let goal = Point2::new(-1.0, 4.0); // A la izquierda: exige un arco
+
+        let path = planner.plan(&start, 0.0, &goal, None, &[], None).unwrap();
+        assert!(nalgebra::distance(&path.last().unwrap().position, &goal) <= 0.2);
+        for pair in path.windows(2) {
+            let chord = nalgebra::distance(&pair[0].position, &pair[1].position);
+            let turn = wrap_angle(pair[1].yaw - pair[0].yaw).abs();
+            assert!(chord > 1e-6); // Sin giros en el sitio
+            if turn > 1e-6 {
+                // Radio del arco que une ambas poses
+                assert!(chord / (2.0 * (turn / 2.0).sin()) >= 0.8 - 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_footprint_must_fit_through_gap() {
+        let config = HybridPlannerConfig { search_margin: 1.0, ..HybridPlannerConfig::default() };
+        let planner = HybridPlanner::new(config);
+        let start = Point2::new(0.0, 0.0);
+        let goal = Point2::new(3.0, 0.0);
+        // Muro en x = 1.5 con un hueco centrado en y = 0
+        let with_gap = |gap: f64| {
+            let half = gap / 2.0;
+            vec![wall(1.5, half + 2.0, 0.2, 4.0), wall(1.5, -half - 2.0, 0.2, 4.0)]
+        };
+
+        // Un punto cabría por 0.55 m, pero la huella de 0.5 m más el margen no
+        assert!(planner.plan(&start, 0.0, &goal, None, &with_gap(0.55), None).is_err());
+
+        let path = planner.plan(&start, 0.0, &goal, None, &with_gap(1.2), None).unwrap();
+        assert!(nalgebra::distance(&path.last().unwrap().position, &goal) <= 0.2);
+    }
+}
@@ -0,0 +1,354 @@
+// 📡 LiDAR Obstacle Extraction Module
+// File: projects/mechros2/src/navigation/lidar_obstacles.rs
+
+use nalgebra::{Isometry3, Matrix2, Point2, Point3, Vector2, Vector3};
+use super::{Obstacle, ObstacleSource, ObstacleType};
+
+#[derive(Debug, Clone)]
+pub struct LidarObstacleConfig {
+    /// Ángulo λ del detector de rupturas adaptativo (rad).
+    pub breakpoint_lambda: f64,
+    /// Ruido de rango del sensor (m), sumado como 3σ al umbral.
+    pub range_sigma: f64,
+    pub min_points: usize,
+    /// Distancia máxima de un punto a la recta de su tramo antes de dividirlo (m).
+    pub split_threshold: f64,
+    pub wall_min_length: f64,
+    pub wall_max_residual: f64,
+    pub obstacle_height: f64,
+    pub min_thickness: f64,
+    /// Puntos a partir de los cuales un segmento tiene confianza plena.
+    pub full_confidence_points: usize,
+}
+
+impl Default for LidarObstacleConfig {
+    fn default() -> Self {
+        Self {
+            breakpoint_lambda: 10f64.to_radians(),
+            range_sigma: 0.03,
+            min_points: 3,
+            split_threshold: 0.1,
+            wall_min_length: 1.0,
+            wall_max_residual: 0.05,
+            obstacle_height: 0.5,
+            min_thickness: 0.1,
+            full_confidence_points: 10,
+        }
+    }
+}
+
+/// Segmenta barridos LiDAR con el detector de rupturas adaptativo (Borges & Aldon),
+/// divide cada grupo en tramos rectos (split-and-merge) y convierte cada tramo en
+/// un `Obstacle` orientado en el marco mundo.
+pub struct LidarObstacleExtractor {
+    config: LidarObstacleConfig,
+}
+
+impl LidarObstacleExtractor {
+    pub fn new(config: LidarObstacleConfig) -> Self {
+        Self { config }
+    }
+
+    /// Divide puntos ordenados por ángulo (marco del robot) en segmentos contiguos.
+    pub fn segment(&self, points: &[Point2<f64>]) -> Vec<Vec<Point2<f64>>> {
+        let mut segments = Vec::new();
+        let mut current: Vec<Point2<f64>> = Vec::new();
+
+        for point in points {
+            if let Some(previous) = current.last() {
+                let range = previous.coords.norm();
+                let delta_phi = angle_between(&previous.coords, &point.coords);
+                let denominator = (self.config.breakpoint_lambda - delta_phi).sin();
+
+                let threshold = if denominator > 1e-6 {
+                    range * delta_phi.sin() / denominator + 3.0 * self.config.range_sigma
+                } else {
+                    0.0 // Separación angular mayor que λ: siempre es ruptura
+                };
+
+                if (point - previous).norm() > threshold {
+                    segments.push(std::mem::take(&mut current));
+                }
+            }
+            current.push(*point);
+        }
+        if !current.is_empty() {
+            segments.push(current);
+        }
+
+        // El barrido es circular: unir el último segmento con el primero si son contiguos
+        if segments.len() > 1 {
+            let first = segments[0][0];
+            let last = *segments[segments.len() - 1].last().unwrap();
+            let delta_phi = angle_between(&last.coords, &first.coords);
+            let denominator = (self.config.breakpoint_lambda - delta_phi).sin();
+            if denominator > 1e-6 {
+                let threshold = last.coords.norm() * delta_phi.sin() / denominator + 3.0 * self.config.range_sigma;
+                if (first - last).norm() <= threshold {
+                    let tail = segments.pop().unwrap();
+                    segments[0].splice(0..0, tail);
+                }
+            }
+        }
+
+        segments.retain(|s| s.len() >= self.config.min_points);
+        segments
+    }
+
+    /// Extrae obstáculos de una nube en el marco del robot, situada con la pose actual.
+    pub fn extract(&self, points: &[Point3<f64>], robot_pose: &Isometry3<f64>) -> Vec<Obstacle> {
+        let points_2d: Vec<Point2<f64>> = points.iter().map(|p| Point2::new(p.x, p.y)).collect();
+
+        self.segment(&points_2d)
+            .iter()
+            .flat_map(|segment| self.split_lines(segment))
+            .map(|line| self.segment_to_obstacle(&line, robot_pose))
+            .collect()
+    }
+
+    /// Split-and-merge: divide un grupo por el punto más alejado de la recta entre
+    /// sus extremos hasta que todos los tramos son rectos, y vuelve a unir los
+    /// tramos consecutivos que juntos siguen siendo rectos. Los tramos comparten
+    /// el punto de corte, de modo que las esquinas quedan cerradas.
+    pub fn split_lines(&self, segment: &[Point2<f64>]) -> Vec<Vec<Point2<f64>>> {
+        let mut lines = Vec::new();
+        self.split(segment, &mut lines);
+
+        let mut merged: Vec<Vec<Point2<f64>>> = Vec::with_capacity(lines.len());
+        for line in lines {
+            if let Some(previous) = merged.last_mut() {
+                let joined: Vec<Point2<f64>> = previous.iter().chain(line.iter().skip(1)).copied().collect();
+                if farthest_from_chord(&joined).1 <= self.config.split_threshold
+                    && line_fit(&joined).residual <= self.config.wall_max_residual
+                {
+                    *previous = joined;
+                    continue;
+                }
+            }
+            merged.push(line);
+        }
+
+        // Grupo cerrado (p. ej. una habitación): el tramo final continúa el inicial
+        if merged.len() > 2 {
+            let max_gap = segment.windows(2).map(|w| nalgebra::distance(&w[0], &w[1])).fold(0.0, f64::max);
+            let (head, tail) = (merged[0][0], *merged[merged.len() - 1].last().unwrap());
+            let joined: Vec<Point2<f64>> = merged[merged.len() - 1].iter().chain(&merged[0]).copied().collect();
+            if nalgebra::distance(&head, &tail) <= max_gap
+                && farthest_from_chord(&joined).1 <= self.config.split_threshold
+                && line_fit(&joined).residual <= self.config.wall_max_residual
+            {
+                merged.pop();
+                merged[0] = joined;
+            }
+        }
+        merged
+    }
+
+    fn split(&self, points: &[Point2<f64>], lines: &mut Vec<Vec<Point2<f64>>>) {
+        let (index, deviation) = farthest_from_chord(points);
+        // Sin dividir si alguna mitad quedaría por debajo del mínimo de puntos
+        let splittable = index + 1 >= self.config.min_points && points.len() - index >= self.config.min_points;
+        if deviation > self.config.split_threshold && splittable {
+            self.split(&points[..=index], lines);
+            self.split(&points[index..], lines);
+        } else {
+            lines.push(points.to_vec());
+        }
+    }
+
+    fn segment_to_obstacle(&self, segment: &[Point2<f64>], robot_pose: &Isometry3<f64>) -> Obstacle {
+        let fit = line_fit(segment);
+        let is_wall = fit.length >= self.config.wall_min_length && fit.residual <= self.config.wall_max_residual;
+
+        // Caja orientada según el eje principal, en el marco del robot y luego del mundo
+        let center = robot_pose * Point3::new(fit.center.x, fit.center.y, 0.0);
+        let axis = robot_pose.rotation * Vector3::new(fit.direction.x, fit.direction.y, 0.0);
+
+        Obstacle {
+            position: Point3::new(center.x, center.y, robot_pose.translation.z + self.config.obstacle_height / 2.0),
+            size: Vector3::new(
+                fit.length.max(self.config.min_thickness),
+                fit.width.max(self.config.min_thickness),
+                self.config.obstacle_height,
+            ),
+            yaw: axis.y.atan2(axis.x),
+            velocity: None,
+            obstacle_type: if is_wall { ObstacleType::Wall } else { ObstacleType::Unknown },
+            confidence: (segment.len() as f32 / self.config.full_confidence_points as f32).min(1.0),
+            source: ObstacleSource::Lidar,
+        }
+    }
+}
+
+impl Default for LidarObstacleExtractor {
+    fn default() -> Self {
+        Self::new(LidarObstacleConfig::default())
+    }
+}
+
+fn angle_between(a: &Vector2<f64>, b: &Vector2<f64>) -> f64 {
+    let cross = a.x * b.y - a.y * b.x;
+    cross.atan2(a.dot(b)).abs()
+}
+
+struct LineFit {
+    center: Point2<f64>,     // Centro de la caja orientada
+    direction: Vector2<f64>, // Eje principal
+    length: f64,             // Extensión a lo largo del eje principal
+    width: f64,              // Extensión perpendicular
+    residual: f64,           // Residuo RMS respecto a la recta
+}
+
+/// Ajuste de recta por componentes principales.
+fn line_fit(points: &[Point2<f64>]) -> LineFit {
+    let n = points.len() as f64;
+    let mean = points.iter().map(|p| p.coords).sum::<Vector2<f64>>() / n;
+
+    let covariance = points.iter()
+        .map(|p| {
+            let d = p.coords - mean;
+            d * d.transpose()
+        })
+        .fold(Matrix2::zeros(), |acc, m| acc + m) / n;
+
+    let eigen = covariance.symmetric_eigen();
+    let (major, minor) = if eigen.eigenvalues[0] >= eigen.eigenvalues[1] { (0, 1) } else { (1, 0) };
+    let direction = eigen.eigenvectors.column(major).into_owned();
+    let normal = Vector2::new(-direction.y, direction.x);
+
+    let range = |axis: &Vector2<f64>| points.iter()
+        .map(|p| (p.coords - mean).dot(axis))
+        .fold((f64::MAX, f64::MIN), |(lo, hi), t| (lo.min(t), hi.max(t)));
+    let (lo, hi) = range(&direction);
+    let (lo_n, hi_n) = range(&normal);
+
+    LineFit {
+        center: Point2::from(mean + direction * (lo + hi) / 2.0 + normal * (lo_n + hi_n) / 2.0),
+        direction,
+        length: hi - lo,
+        width: hi_n - lo_n,
+        residual: eigen.eigenvalues[minor].max(0.0).sqrt(),
+    }
+}
+
+/// Índice y distancia del punto más alejado de la cuerda entre los extremos.
+/// Si los extremos coinciden (grupo cerrado), se mide la distancia al primero.
+fn farthest_from_chord(points: &[Point2<f64>]) -> (usize, f64) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let chord = last - first;
+    let length = chord.norm();
+    points.iter().enumerate()
+        .map(|(i, p)| {
+            let offset = p - first;
+            let distance = if length > 1e-9 { (chord.perp(&offset) / length).abs() } else { offset.norm() };
+            (i, distance)
+        })
+        .fold((0, 0.0), |best, current| if current.1 > best.1 { current } else { best })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Translation3, UnitQuaternion};
+
+    #[test]
+    fn test_wall_and_cluster_segmentation() {
+        let extractor = LidarObstacleExtractor::default();
+        let mut points = Vec::new();
+
+        // Pared frontal a 2 m, de y=-1 a y=1
+        for i in 0..=40 {
+            points.push(Point3::new(2.0, -1.0 + i as f64 * 0.05, 0.0));
+        }
+        // Objeto pequeño a la izquierda, separado de la pared
+        for i in 0..5 {
+            points.push(Point3::new(0.5 + i as f64 * 0.03, 1.5, 0.0));
+        }
+
+        let obstacles = extractor.extract(&points, &Isometry3::identity());
+        assert_eq!(obstacles.len(), 2);
+        assert!(matches!(obstacles[0].obstacle_type, ObstacleType::Wall));
+        assert!((obstacles[0].size.x - 2.0).abs() < 1e-6);
+        assert!((obstacles[0].yaw.abs() - std::f64::consts::FRAC_PI_2).abs() < 1e-6);
+        assert!(matches!(obstacles[1].obstacle_type, ObstacleType::Unknown));
+    }
+
+    #[test]
+    fn test_obstacles_in_world_frame() {
+        let extractor = LidarObstacleExtractor::default();
+        let points: Vec<_> = (0..5).map(|i| Point3::new(1.0, -0.05 + i as f64 * 0.025, 0.0)).collect();
+
+        // Robot en (10, 5) mirando hacia +Y
+        let pose = Isometry3::from_parts(
+            Translation3::new(10.0, 5.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, std::f64::consts::FRAC_PI_2),
+        );
+        let obstacles = extractor.extract(&points, &pose);
+
+        assert_eq!(obstacles.len(), 1);
+        assert!((obstacles[0].position.x - 10.0).abs() < 1e-6);
+        assert!((obstacles[0].position.y - 6.0).abs() < 1e-6);
+    }
+
+    /// Barrido de 360 haces a 1° desde el origen del robot.
+    fn scan(range: impl Fn(f64) -> f64) -> Vec<Point3<f64>> {
+        (0..360).map(|i| {
+            let angle = (i as f64).to_radians();
+            let r = range(angle);
+            Point3::new(r * angle.cos(), r * angle.sin(), 0.0)
+        }).collect()
+    }
+
+    fn distance_to(obstacle: &Obstacle, point: &Point2<f64>) -> f64 {
+        nalgebra::distance(point, &super::super::avoidance::closest_point(obstacle, point))
+    }
+
+    #[test]
+    fn test_enclosed_room_splits_into_walls() {
+        let extractor = LidarObstacleExtractor::default();
+        // Habitación x ∈ [-2, 4], y ∈ [-1.5, 2.5] alrededor del robot
+        let room = scan(|angle: f64| {
+            let (c, s) = (angle.cos(), angle.sin());
+            let tx = if c > 1e-9 { 4.0 / c } else if c < -1e-9 { -2.0 / c } else { f64::INFINITY };
+            let ty = if s > 1e-9 { 2.5 / s } else if s < -1e-9 { -1.5 / s } else { f64::INFINITY };
+            tx.min(ty)
+        });
+        let pose = Isometry3::translation(10.0, 5.0, 0.0);
+        let robot = Point2::new(10.0, 5.0);
+
+        let obstacles = extractor.extract(&room, &pose);
+        assert_eq!(obstacles.len(), 4);
+        for obstacle in &obstacles {
+            assert!(matches!(obstacle.obstacle_type, ObstacleType::Wall));
+            assert!(distance_to(obstacle, &robot) >= 1.4); // El robot queda libre (pared más cercana a 1.5 m)
+        }
+
+        // Barrido circular de 5 m, como el simulado: tramos alrededor, nunca sobre el robot
+        let circle = extractor.extract(&scan(|_| 5.0), &pose);
+        assert!(circle.len() > 4);
+        for obstacle in &circle {
+            assert!(distance_to(obstacle, &robot) > 4.5);
+            assert!(obstacle.size.x < 3.0);
+        }
+    }
+
+    #[test]
+    fn test_diagonal_wall_is_oriented() {
+        let extractor = LidarObstacleExtractor::default();
+        let points: Vec<_> = (0..=40).map(|i| {
+            let t = i as f64 / 40.0;
+            Point3::new(2.0 + 2.0 * t, -1.0 + 2.0 * t, 0.0)
+        }).collect();
+
+        let obstacles = extractor.extract(&points, &Isometry3::identity());
+        assert_eq!(obstacles.len(), 1);
+        let wall = &obstacles[0];
+        assert!(matches!(wall.obstacle_type, ObstacleType::Wall));
+        assert!((wall.size.x - 8f64.sqrt()).abs() < 1e-6);
+        assert!((wall.size.y - extractor.config.min_thickness).abs() < 1e-9);
+        assert!(((2.0 * wall.yaw).sin() - 1.0).abs() < 1e-6); // ±45°
+
+        // Una caja alineada con los ejes cubriría este punto; la orientada no
+        assert!(distance_to(wall, &Point2::new(3.0, 1.0)) > 0.6);
+    }
+}
@@ -0,0 +1,289 @@
+// 💾 Map Server I/O Module
+// File: projects/mechros2/src/navigation/map_io.rs
+
+use std::path::{Component, Path, PathBuf};
+use nalgebra::Point2;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use super::occupancy_grid::{OccupancyGrid, OccupancyGridConfig};
+
+// Valores de gris escritos por map_saver
+const PGM_FREE: u8 = 254;
+const PGM_OCCUPIED: u8 = 0;
+const PGM_UNKNOWN: u8 = 205;
+// Umbral libre guardado: deja el gris 205 (ocupación 0.196) como desconocido al recargar
+const SAVED_FREE_THRESH: f64 = 0.196;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapMode {
+    Trinary, // Libre / ocupado / desconocido
+    Scale,   // Valores intermedios como probabilidad
+}
+
+/// Fichero YAML de `map_server` (formato Nav2).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapMetadata {
+    pub image: String,
+    #[serde(default = "default_mode")]
+    pub mode: MapMode,
+    pub resolution: f64,
+    pub origin: [f64; 3], // x, y, yaw de la esquina inferior izquierda
+    #[serde(default)]
+    pub negate: i32,
+    pub occupied_thresh: f64,
+    pub free_thresh: f64,
+}
+
+fn default_mode() -> MapMode {
+    MapMode::Trinary
+}
+
+/// Resuelve una ruta de mapa recibida por comando dentro de `maps_directory`.
+/// Solo se aceptan rutas relativas sin `..`, para que un comando remoto no pueda
+/// leer ni escribir fuera del directorio de mapas.
+pub fn resolve_map_path(maps_directory: &Path, requested: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let relative = Path::new(requested);
+    let mut has_name = false;
+    for component in relative.components() {
+        match component {
+            Component::Normal(_) => has_name = true,
+            Component::CurDir => {}
+            Component::ParentDir => return Err(format!("Ruta de mapa con '..' no permitida: {}", requested).into()),
+            Component::RootDir | Component::Prefix(_) => return Err(format!("Ruta de mapa absoluta no permitida: {}", requested).into()),
+        }
+    }
+    if !has_name {
+        return Err(format!("Ruta de mapa vacía: '{}'", requested).into());
+    }
+    Ok(maps_directory.join(relative))
+}
+
+/// Carga un mapa `map_server` (YAML + PGM). La imagen se busca relativa al YAML.
+/// `config` aporta los parámetros de actualización; la resolución se toma del fichero.
+pub fn load_map(yaml_path: &Path, config: &OccupancyGridConfig) -> Result<OccupancyGrid, Box<dyn std::error::Error>> {
+    let metadata: MapMetadata = serde_yaml::from_str(&std::fs::read_to_string(yaml_path)?)?;
+    if metadata.resolution <= 0.0 {
+        return Err(format!("Resolución de mapa inválida: {}", metadata.resolution).into());
+    }
+    if metadata.origin[2].abs() > 1e-9 {
+        warn!("🗺️ Rotación del origen del mapa ({:.3} rad) no soportada, se ignora", metadata.origin[2]);
+    }
+
+    let image_path = yaml_path.parent().unwrap_or(Path::new(".")).join(&metadata.image);
+    let image = Pgm::parse(&std::fs::read(&image_path)?)?;
+
+    let config = OccupancyGridConfig { resolution: metadata.resolution, ..config.clone() };
+    let mut log_odds = vec![0.0; image.width * image.height];
+    for row in 0..image.height {
+        // La primera fila de la imagen es el borde superior del mapa
+        let y = image.height - 1 - row;
+        for x in 0..image.width {
+            let shade = image.pixels[row * image.width + x] as f64 / image.max_value as f64;
+            let occupancy = if metadata.negate != 0 { shade } else { 1.0 - shade };
+            log_odds[y * image.width + x] = cell_log_odds(occupancy, &metadata, &config);
+        }
+    }
+
+    OccupancyGrid::from_log_odds(
+        config,
+        Point2::new(metadata.origin[0], metadata.origin[1]),
+        image.width,
+        image.height,
+        log_odds,
+    )
+}
+
+fn cell_log_odds(occupancy: f64, metadata: &MapMetadata, config: &OccupancyGridConfig) -> f32 {
+    if occupancy > metadata.occupied_thresh {
+        config.log_odds_max
+    } else if occupancy < metadata.free_thresh {
+        config.log_odds_min
+    } else {
+        match metadata.mode {
+            MapMode::Trinary => 0.0,
+            MapMode::Scale => {
+                let p = ((occupancy - metadata.free_thresh) / (metadata.occupied_thresh - metadata.free_thresh))
+                    .clamp(0.01, 0.99) as f32;
+                let l = (p / (1.0 - p)).ln().clamp(config.log_odds_min, config.log_odds_max);
+                // 0 significa desconocido: una celda observada nunca vale exactamente 0
+                if l == 0.0 { f32::EPSILON } else { l }
+            }
+        }
+    }
+}
+
+/// Guarda el mapa en formato `map_server`: `<nombre>.yaml` y `<nombre>.pgm` en el mismo directorio.
+pub fn save_map(grid: &OccupancyGrid, yaml_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let image_path = yaml_path.with_extension("pgm");
+    let image_name = image_path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Ruta de mapa inválida: {}", yaml_path.display()))?
+        .to_string();
+
+    let mut pgm = format!("P5\n# CREATOR: mechros2 {:.3} m/pix\n{} {}\n255\n", grid.resolution(), grid.width(), grid.height()).into_bytes();
+    pgm.reserve(grid.width() * grid.height());
+    for y in (0..grid.height()).rev() {
+        for x in 0..grid.width() {
+            pgm.push(if grid.is_occupied((x, y)) {
+                PGM_OCCUPIED
+            } else if grid.is_free((x, y)) {
+                PGM_FREE
+            } else {
+                PGM_UNKNOWN
+            });
+        }
+    }
+
+    let metadata = MapMetadata {
+        image: image_name,
+        mode: MapMode::Trinary,
+        resolution: grid.resolution(),
+        origin: [grid.origin().x, grid.origin().y, 0.0],
+        negate: 0,
+        occupied_thresh: grid.config().occupied_probability as f64,
+        free_thresh: SAVED_FREE_THRESH,
+    };
+
+    std::fs::write(&image_path, pgm)?;
+    std::fs::write(yaml_path, serde_yaml::to_string(&metadata)?)?;
+    Ok(())
+}
+
+/// Imagen PGM en escala de grises (P5 binario o P2 ASCII).
+struct Pgm {
+    width: usize,
+    height: usize,
+    max_value: u16,
+    pixels: Vec<u16>,
+}
+
+impl Pgm {
+    fn parse(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut pos = 0;
+        let magic = next_token(bytes, &mut pos).ok_or("PGM vacío")?;
+        let binary = match magic.as_str() {
+            "P5" => true,
+            "P2" => false,
+            other => return Err(format!("Formato de imagen no soportado: {} (se espera PGM P5/P2)", other).into()),
+        };
+        let mut header = [0usize; 3];
+        for value in header.iter_mut() {
+            *value = next_token(bytes, &mut pos).ok_or("Cabecera PGM incompleta")?.parse()?;
+        }
+        let [width, height, max_value] = header;
+        if max_value == 0 || max_value > u16::MAX as usize {
+            return Err(format!("Valor máximo PGM inválido: {}", max_value).into());
+        }
+
+        let count = width * height;
+        let pixels: Vec<u16> = if binary {
+            pos += 1; // Un único separador tras la cabecera
+            let sample_size = if max_value > 255 { 2 } else { 1 };
+            let data = bytes.get(pos..pos + count * sample_size).ok_or("Datos PGM truncados")?;
+            if sample_size == 2 {
+                data.as_chunks::<2>().0.iter().map(|&pair| u16::from_be_bytes(pair)).collect()
+            } else {
+                data.iter().map(|&v| v as u16).collect()
+            }
+        } else {
+            (0..count)
+                .map(|_| next_token(bytes, &mut pos).ok_or("Datos PGM truncados")?.parse::<u16>().map_err(Into::into))
+                .collect::<Result<_, Box<dyn std::error::Error>>>()?
+        };
+
+        Ok(Self { width, height, max_value: max_value as u16, pixels })
+    }
+}
+
+/// Siguiente token de la cabecera, saltando espacios y comentarios `#`.
+fn next_token(bytes: &[u8], pos: &mut usize) -> Option<String> {
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if bytes.get(*pos) != Some(&b'#') {
+            break;
+        }
+        while *pos < bytes.len() && bytes[*pos] != b'\n' {
+            *pos += 1;
+        }
+    }
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    (*pos > start).then(|| String::from_utf8_lossy(&bytes[start..*pos]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mechros2_map_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_ascii_pgm_with_negate() {
+        let dir = temp_dir("negate");
+        std::fs::write(dir.join("map.yaml"), "image: map.pgm\nresolution: 0.5\norigin: [-1.0, 2.0, 0.0]\nnegate: 1\noccupied_thresh: 0.65\nfree_thresh: 0.196\n").unwrap();
+        // Con negate, blanco = ocupado. Fila superior: ocupado, desconocido; inferior: libre, libre
+        std::fs::write(dir.join("map.pgm"), "P2\n# comentario\n2 2\n255\n255 128\n0 0\n").unwrap();
+
+        let grid = load_map(&dir.join("map.yaml"), &OccupancyGridConfig::default()).unwrap();
+        assert_eq!((grid.width(), grid.height()), (2, 2));
+        assert_eq!(grid.resolution(), 0.5);
+        assert_eq!(grid.origin(), Point2::new(-1.0, 2.0));
+        assert!(grid.is_occupied((0, 1)));
+        assert!(grid.probability((1, 1)).is_none());
+        assert!(grid.is_free((0, 0)) && grid.is_free((1, 0)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_reload_round_trip() {
+        let dir = temp_dir("roundtrip");
+        let config = OccupancyGridConfig { resolution: 0.1, ..OccupancyGridConfig::default() };
+        let mut log_odds = vec![0.0; 12];
+        log_odds[0] = config.log_odds_max;
+        log_odds[5] = config.log_odds_min;
+        let grid = OccupancyGrid::from_log_odds(config.clone(), Point2::new(3.0, -4.0), 4, 3, log_odds).unwrap();
+
+        save_map(&grid, &dir.join("live.yaml")).unwrap();
+        let pgm = std::fs::read(dir.join("live.pgm")).unwrap();
+        assert!(pgm.starts_with(b"P5"));
+        // Fila inferior del mapa = última fila de la imagen
+        assert_eq!(pgm[pgm.len() - 4], PGM_OCCUPIED);
+
+        let loaded = load_map(&dir.join("live.yaml"), &config).unwrap();
+        for cell in (0..3).flat_map(|y| (0..4).map(move |x| (x, y))) {
+            assert_eq!(loaded.is_occupied(cell), grid.is_occupied(cell));
+            assert_eq!(loaded.is_free(cell), grid.is_free(cell));
+            assert_eq!(loaded.probability(cell).is_none(), grid.probability(cell).is_none());
+        }
+        assert_eq!(loaded.origin(), grid.origin());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_map_paths_stay_inside_maps_directory() {
+        let maps = Path::new("/var/lib/mechros2/maps");
+        assert_eq!(resolve_map_path(maps, "office.yaml").unwrap(), maps.join("office.yaml"));
+        assert_eq!(resolve_map_path(maps, "./floor1/office.yaml").unwrap(), maps.join("floor1/office.yaml"));
+
+        assert!(resolve_map_path(maps, "/etc/passwd").is_err());
+        assert!(resolve_map_path(maps, "../secrets.yaml").is_err());
+        assert!(resolve_map_path(maps, "floor1/../../secrets.yaml").is_err());
+        assert!(resolve_map_path(maps, "").is_err());
+        assert!(resolve_map_path(maps, ".").is_err());
+    }
+
+    #[test]
+    fn test_rejects_unsupported_image() {
+        assert!(Pgm::parse(b"P6\n1 1\n255\n\x00\x00\x00").is_err());
+        assert!(Pgm::parse(b"P5\n2 2\n255\n\x00").is_err());
+    }
+}
@@ -0,0 +1,361 @@
+// 🧱 Occupancy Grid Mapping Module
+// File: projects/mechros2/src/navigation/occupancy_grid.rs
+
+use nalgebra::{Isometry3, Point2, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use crate::sensors::LidarData;
+use super::{Obstacle, ObstacleSource, ObstacleType};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OccupancyGridConfig {
+    pub resolution: f64,     // m por celda
+    pub initial_size: f64,   // Lado del mapa inicial, centrado en el origen (m)
+    pub growth_margin: f64,  // Ampliación mínima al crecer (m)
+    pub max_size: f64,       // Lado máximo admitido; los haces fuera se descartan (m)
+    pub log_odds_hit: f32,
+    pub log_odds_miss: f32,
+    pub log_odds_min: f32,
+    pub log_odds_max: f32,
+    pub occupied_probability: f32,
+    pub free_probability: f32,
+}
+
+impl Default for OccupancyGridConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 0.05,
+            initial_size: 20.0,
+            growth_margin: 5.0,
+            max_size: 500.0,
+            log_odds_hit: 0.85,
+            log_odds_miss: -0.4,
+            log_odds_min: -2.0,
+            log_odds_max: 3.5,
+            occupied_probability: 0.65,
+            free_probability: 0.25,
+        }
+    }
+}
+
+/// Mapa de ocupación 2D en log-odds (0 = desconocido), en el marco mundo.
+/// Las celdas se indexan por fila: `y * width + x`, con (0, 0) en `origin`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OccupancyGrid {
+    config: OccupancyGridConfig,
+    origin: Point2<f64>,
+    width: usize,
+    height: usize,
+    log_odds: Vec<f32>,
+}
+
+impl OccupancyGrid {
+    pub fn new(config: OccupancyGridConfig) -> Self {
+        let cells = (config.initial_size / config.resolution).ceil() as usize;
+        let half = cells as f64 * config.resolution / 2.0;
+        Self {
+            origin: Point2::new(-half, -half),
+            width: cells,
+            height: cells,
+            log_odds: vec![0.0; cells * cells],
+            config,
+        }
+    }
+
+    /// Mapa con valores ya conocidos (p. ej. cargado de disco), en log-odds.
+    pub fn from_log_odds(config: OccupancyGridConfig, origin: Point2<f64>, width: usize, height: usize, log_odds: Vec<f32>) -> Result<Self, Box<dyn std::error::Error>> {
+        if log_odds.len() != width * height {
+            return Err(format!("Tamaño de mapa inconsistente: {} celdas para {}x{}", log_odds.len(), width, height).into());
+        }
+        Ok(Self { config, origin, width, height, log_odds })
+    }
+
+    pub fn config(&self) -> &OccupancyGridConfig {
+        &self.config
+    }
+
+    pub fn origin(&self) -> Point2<f64> {
+        self.origin
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.config.resolution
+    }
+
+    pub fn cell_of(&self, point: &Point2<f64>) -> Option<(usize, usize)> {
+        let (x, y) = self.signed_cell(point);
+        (x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height)
+            .then_some((x as usize, y as usize))
+    }
+
+    pub fn cell_center(&self, cell: (usize, usize)) -> Point2<f64> {
+        Point2::new(
+            self.origin.x + (cell.0 as f64 + 0.5) * self.config.resolution,
+            self.origin.y + (cell.1 as f64 + 0.5) * self.config.resolution,
+        )
+    }
+
+    pub fn log_odds(&self, cell: (usize, usize)) -> f32 {
+        self.log_odds[cell.1 * self.width + cell.0]
+    }
+
+    /// Probabilidad de ocupación; `None` si la celda nunca se ha observado.
+    pub fn probability(&self, cell: (usize, usize)) -> Option<f32> {
+        let l = self.log_odds(cell);
+        (l != 0.0).then(|| 1.0 - 1.0 / (1.0 + l.exp()))
+    }
+
+    pub fn is_occupied(&self, cell: (usize, usize)) -> bool {
+        self.probability(cell).is_some_and(|p| p >= self.config.occupied_probability)
+    }
+
+    pub fn is_free(&self, cell: (usize, usize)) -> bool {
+        self.probability(cell).is_some_and(|p| p <= self.config.free_probability)
+    }
+
+    /// Celdas ocupadas cuyo centro está dentro del rectángulo dado.
+    pub fn occupied_cells_in(&self, min: &Point2<f64>, max: &Point2<f64>) -> Vec<(usize, usize)> {
+        let (x0, y0) = self.signed_cell(min);
+        let (x1, y1) = self.signed_cell(max);
+        let clamp_x = |x: i64| x.clamp(0, self.width as i64 - 1) as usize;
+        let clamp_y = |y: i64| y.clamp(0, self.height as i64 - 1) as usize;
+
+        let mut cells = Vec::new();
+        for y in clamp_y(y0)..=clamp_y(y1) {
+            for x in clamp_x(x0)..=clamp_x(x1) {
+                if self.is_occupied((x, y)) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Celdas ocupadas a menos de `radius` de `center`, como obstáculos del tamaño de una celda.
+    pub fn obstacles_near(&self, center: &Point3<f64>, radius: f64) -> Vec<Obstacle> {
+        let c = center.xy();
+        let r = nalgebra::Vector2::repeat(radius);
+        self.occupied_cells_in(&(c - r), &(c + r))
+            .into_iter()
+            .filter_map(|cell| {
+                let p = self.cell_center(cell);
+                (nalgebra::distance(&p, &c) <= radius).then(|| Obstacle {
+                    position: Point3::new(p.x, p.y, center.z),
+                    size: Vector3::new(self.config.resolution, self.config.resolution, 0.5),
+                    yaw: 0.0,
+                    velocity: None,
+                    obstacle_type: ObstacleType::Static,
+                    confidence: self.probability(cell).unwrap_or(0.0),
+                    source: ObstacleSource::Lidar,
+                })
+            })
+            .collect()
+    }
+
+    /// Integra un barrido trazando cada haz desde la pose del sensor: las celdas
+    /// atravesadas se marcan libres y la del impacto, ocupada. Los haces sin
+    /// retorno solo despejan hasta `range_max`.
+    pub fn update_scan(&mut self, scan: &LidarData, sensor_pose: &Isometry3<f64>) {
+        let origin = Point2::from(sensor_pose.translation.vector.xy());
+        if !self.ensure_contains(&origin) {
+            return;
+        }
+
+        for (i, &range) in scan.ranges.iter().enumerate() {
+            let angle = (scan.angle_min + i as f32 * scan.angle_increment) as f64;
+            let (hit, length) = if range.is_finite() && range >= scan.range_min && range <= scan.range_max {
+                (true, range as f64)
+            } else if range.is_nan() || range < scan.range_min {
+                continue;
+            } else {
+                (false, scan.range_max as f64)
+            };
+
+            let end = sensor_pose * Point3::new(length * angle.cos(), length * angle.sin(), 0.0);
+            let end = end.xy();
+            if !self.ensure_contains(&end) {
+                continue;
+            }
+
+            // Las coordenadas pueden cambiar al crecer: se calculan tras `ensure_contains`
+            let Some(start_cell) = self.cell_of(&origin) else { continue };
+            let Some(end_cell) = self.cell_of(&end) else { continue };
+
+            let cells = bresenham(start_cell, end_cell);
+            let last = cells.len() - 1;
+            for (j, cell) in cells.into_iter().enumerate() {
+                let delta = if j == last && hit { self.config.log_odds_hit } else { self.config.log_odds_miss };
+                self.add(cell, delta);
+            }
+        }
+    }
+
+    fn add(&mut self, cell: (usize, usize), delta: f32) {
+        let value = &mut self.log_odds[cell.1 * self.width + cell.0];
+        *value = (*value + delta).clamp(self.config.log_odds_min, self.config.log_odds_max);
+        // Evitar que una celda observada vuelva a leerse como desconocida
+        if *value == 0.0 {
+            *value = f32::EPSILON.copysign(delta);
+        }
+    }
+
+    fn signed_cell(&self, point: &Point2<f64>) -> (i64, i64) {
+        (
+            ((point.x - self.origin.x) / self.config.resolution).floor() as i64,
+            ((point.y - self.origin.y) / self.config.resolution).floor() as i64,
+        )
+    }
+
+    /// Amplía el mapa si el punto queda fuera; `false` si superaría el tamaño máximo.
+    fn ensure_contains(&mut self, point: &Point2<f64>) -> bool {
+        if !point.x.is_finite() || !point.y.is_finite() {
+            return false;
+        }
+        let (x, y) = self.signed_cell(point);
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            return true;
+        }
+
+        let margin = (self.config.growth_margin / self.config.resolution).ceil() as i64;
+        let grow_left = if x < 0 { -x + margin } else { 0 };
+        let grow_down = if y < 0 { -y + margin } else { 0 };
+        let grow_right = if x >= self.width as i64 { x - self.width as i64 + 1 + margin } else { 0 };
+        let grow_up = if y >= self.height as i64 { y - self.height as i64 + 1 + margin } else { 0 };
+
+        let new_width = self.width + (grow_left + grow_right) as usize;
+        let new_height = self.height + (grow_down + grow_up) as usize;
+        let max_cells = (self.config.max_size / self.config.resolution).ceil() as usize;
+        if new_width > max_cells || new_height > max_cells {
+            return false;
+        }
+
+        let mut log_odds = vec![0.0; new_width * new_height];
+        for row in 0..self.height {
+            let dst = (row + grow_down as usize) * new_width + grow_left as usize;
+            log_odds[dst..dst + self.width].copy_from_slice(&self.log_odds[row * self.width..(row + 1) * self.width]);
+        }
+
+        self.origin -= nalgebra::Vector2::new(grow_left as f64, grow_down as f64) * self.config.resolution;
+        self.width = new_width;
+        self.height = new_height;
+        self.log_odds = log_odds;
+        true
+    }
+
+    /// Datos en formato `nav_msgs/OccupancyGrid`: -1 desconocido, 0-100 probabilidad.
+    pub fn to_ros_data(&self) -> Vec<i8> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|cell| match self.probability(cell) {
+                Some(p) => (p * 100.0).round() as i8,
+                None => -1,
+            })
+            .collect()
+    }
+}
+
+/// Celdas atravesadas por el segmento entre dos celdas, ambas incluidas.
+fn bresenham(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    let mut cells = Vec::with_capacity((dx - dy) as usize + 1);
+    loop {
+        cells.push((x as usize, y as usize));
+        if x == x1 && y == y1 {
+            return cells;
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Translation3, UnitQuaternion};
+
+    fn scan(ranges: Vec<f32>, angle_min: f32, angle_increment: f32) -> LidarData {
+        LidarData {
+            intensities: vec![0.0; ranges.len()],
+            angle_max: angle_min + angle_increment * (ranges.len() as f32 - 1.0),
+            ranges,
+            angle_min,
+            angle_increment,
+            range_min: 0.1,
+            range_max: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_ray_casting_marks_free_and_occupied() {
+        let mut grid = OccupancyGrid::new(OccupancyGridConfig::default());
+        // Robot en (1, 1) mirando hacia +y; un haz frontal choca a 2 m
+        let pose = Isometry3::from_parts(
+            Translation3::new(1.0, 1.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, std::f64::consts::FRAC_PI_2),
+        );
+        for _ in 0..3 {
+            grid.update_scan(&scan(vec![2.0], 0.0, 0.01), &pose);
+        }
+
+        let hit = grid.cell_of(&Point2::new(1.0, 3.0)).unwrap();
+        let path = grid.cell_of(&Point2::new(1.0, 2.0)).unwrap();
+        let unknown = grid.cell_of(&Point2::new(3.0, 1.0)).unwrap();
+        assert!(grid.is_occupied(hit));
+        assert!(grid.is_free(path));
+        assert!(grid.probability(unknown).is_none());
+
+        let obstacles = grid.obstacles_near(&Point3::new(1.0, 1.0, 0.0), 2.5);
+        assert_eq!(obstacles.len(), 1);
+        assert!((obstacles[0].position.y - 3.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_grid_grows_and_keeps_data() {
+        let mut grid = OccupancyGrid::new(OccupancyGridConfig { initial_size: 4.0, ..OccupancyGridConfig::default() });
+        let pose = Isometry3::identity();
+        grid.update_scan(&scan(vec![1.0], 0.0, 0.01), &pose);
+        grid.update_scan(&scan(vec![1.0], 0.0, 0.01), &pose);
+        let before = grid.cell_of(&Point2::new(1.0, 0.0)).map(|c| grid.log_odds(c)).unwrap();
+
+        // Un impacto a 8 m hacia -x obliga a ampliar el mapa por la izquierda
+        grid.update_scan(&scan(vec![8.0], std::f32::consts::PI, 0.01), &pose);
+        assert!(grid.origin().x <= -8.0);
+        assert_eq!(grid.obstacles_near(&Point3::new(-8.0, 0.0, 0.0), 0.1).len(), 1);
+        let after = grid.log_odds(grid.cell_of(&Point2::new(1.0, 0.0)).unwrap());
+        assert_eq!(before, after);
+
+        // Nunca se observa nada fuera: la mayoría de celdas siguen desconocidas
+        let data = grid.to_ros_data();
+        assert_eq!(data.len(), grid.width() * grid.height());
+        assert!(data.iter().filter(|&&v| v == -1).count() > data.len() / 2);
+    }
+
+    #[test]
+    fn test_bresenham_endpoints() {
+        let cells = bresenham((0, 0), (5, 2));
+        assert_eq!(cells.first(), Some(&(0, 0)));
+        assert_eq!(cells.last(), Some(&(5, 2)));
+        assert_eq!(cells.len(), 6);
+    }
+}
@@ -0,0 +1,276 @@
+// 🏎️ Path Tracking Controller Module
+// File: projects/mechros2/src/navigation/path_tracking.rs
+
+use nalgebra::{Point2, Point3, Vector2};
+use serde::{Deserialize, Serialize};
+use crate::sensors::orientation::wrap_angle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackingLaw {
+    PurePursuit, // Arco hasta un punto adelantado de la ruta
+    Stanley,     // Error de rumbo + corrección del error lateral
+}
+
+#[derive(Debug, Clone)]
+pub struct PathTrackerConfig {
+    pub law: TrackingLaw,
+    pub lookahead_min: f64,      // m
+    pub lookahead_max: f64,
+    pub lookahead_time: f64,     // Lookahead = velocidad · tiempo, acotado (s)
+    pub max_lateral_accel: f64,  // Limita la velocidad en curva: v ≤ √(a/|κ|) (m/s²)
+    pub approach_decel: f64,     // Deceleración al aproximarse a un punto de parada (m/s²)
+    pub stanley_gain: f64,
+    pub stanley_softening: f64,  // Evita la singularidad a velocidad nula (m/s)
+    pub rotate_in_place_angle: f64, // Por encima de este error de rumbo se gira sin avanzar (rad)
+}
+
+impl Default for PathTrackerConfig {
+    fn default() -> Self {
+        Self {
+            law: TrackingLaw::PurePursuit,
+            lookahead_min: 0.4,
+            lookahead_max: 2.0,
+            lookahead_time: 1.0,
+            max_lateral_accel: 0.6,
+            approach_decel: 0.8,
+            stanley_gain: 1.5,
+            stanley_softening: 0.2,
+            rotate_in_place_angle: 90f64.to_radians(),
+        }
+    }
+}
+
+/// Consigna para un robot diferencial: avance (m/s) y giro (rad/s) en su marco.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackingCommand {
+    pub linear: f64,
+    pub angular: f64,
+    pub lookahead: Point3<f64>,
+    pub remaining: f64, // Distancia restante sobre la ruta (m)
+}
+
+/// Sigue una ruta poligonal completa en lugar de apuntar al waypoint actual.
+pub struct PathTracker {
+    config: PathTrackerConfig,
+    max_linear_speed: f64,
+    max_angular_speed: f64,
+    path: Vec<Point2<f64>>,
+    height: f64,
+    segment: usize, // Segmento en curso; el progreso nunca retrocede
+}
+
+impl PathTracker {
+    pub fn new(config: PathTrackerConfig, max_linear_speed: f64, max_angular_speed: f64) -> Self {
+        Self {
+            config,
+            max_linear_speed,
+            max_angular_speed,
+            path: Vec::new(),
+            height: 0.0,
+            segment: 0,
+        }
+    }
+
+    /// Nueva ruta: posición de partida seguida de los waypoints.
+    pub fn set_path(&mut self, start: &Point3<f64>, waypoints: &[Point3<f64>]) {
+        self.path = std::iter::once(start).chain(waypoints).map(|p| p.xy()).collect();
+        self.height = waypoints.last().unwrap_or(start).z;
+        self.segment = 0;
+    }
+
+    pub fn has_path(&self) -> bool {
+        self.path.len() >= 2
+    }
+
+    /// Waypoints aún por superar (los vértices de la ruta tras la partida).
+    pub fn remaining_waypoints(&self) -> usize {
+        self.path.len().saturating_sub(self.segment + 1)
+    }
+
+    /// `speed` es la velocidad actual del robot; `stop_at_end` activa la aproximación
+    /// con deceleración al último punto. `max_speed` limita la consigna.
+    pub fn compute(&mut self, position: &Point3<f64>, yaw: f64, speed: f64, max_speed: f64, stop_at_end: bool) -> Option<TrackingCommand> {
+        if !self.has_path() {
+            return None;
+        }
+        let robot = position.xy();
+        let (segment, projection) = self.project(&robot);
+        self.segment = segment;
+
+        let lookahead_distance = (speed.abs() * self.config.lookahead_time)
+            .clamp(self.config.lookahead_min, self.config.lookahead_max);
+        let lookahead = self.point_along(segment, &projection, lookahead_distance);
+        let remaining = self.distance_to_end(segment, &projection);
+
+        // Objetivo en el marco del robot
+        let delta = lookahead - robot;
+        let local = Vector2::new(
+            delta.x * yaw.cos() + delta.y * yaw.sin(),
+            -delta.x * yaw.sin() + delta.y * yaw.cos(),
+        );
+        let bearing = local.y.atan2(local.x);
+
+        let curvature = match self.config.law {
+            TrackingLaw::PurePursuit => {
+                let d2 = local.norm_squared();
+                if d2 < 1e-9 { 0.0 } else { 2.0 * local.y / d2 }
+            }
+            TrackingLaw::Stanley => {
+                let direction = self.segment_direction(segment);
+                let heading_error = wrap_angle(direction.y.atan2(direction.x) - yaw);
+                // Error lateral con signo: positivo si la ruta queda a la izquierda
+                let offset = projection - robot;
+                let cross_track = direction.perp(&offset);
+                let steering = heading_error
+                    + (self.config.stanley_gain * cross_track).atan2(speed.abs() + self.config.stanley_softening);
+                // Arco equivalente que alcanza la distancia de lookahead con ese ángulo
+                2.0 * steering.clamp(-std::f64::consts::FRAC_PI_2, std::f64::consts::FRAC_PI_2).sin() / lookahead_distance
+            }
+        };
+
+        // Objetivo detrás del robot: girar sobre sí mismo
+        if bearing.abs() > self.config.rotate_in_place_angle && remaining > 1e-3 {
+            return Some(TrackingCommand {
+                linear: 0.0,
+                angular: self.max_angular_speed.copysign(bearing),
+                lookahead: Point3::new(lookahead.x, lookahead.y, self.height),
+                remaining,
+            });
+        }
+
+        let mut linear = max_speed.min(self.max_linear_speed);
+        if curvature.abs() > 1e-9 {
+            linear = linear.min((self.config.max_lateral_accel / curvature.abs()).sqrt());
+        }
+        if stop_at_end {
+            linear = linear.min((2.0 * self.config.approach_decel * remaining).sqrt());
+        }
+
+        // Si el giro satura, se reduce el avance para mantener el arco
+        let mut angular = linear * curvature;
+        if angular.abs() > self.max_angular_speed {
+            angular = self.max_angular_speed.copysign(angular);
+            linear = self.max_angular_speed / curvature.abs();
+        }
+
+        Some(TrackingCommand {
+            linear,
+            angular,
+            lookahead: Point3::new(lookahead.x, lookahead.y, self.height),
+            remaining,
+        })
+    }
+
+    /// Punto de la ruta más cercano al robot, desde el segmento en curso hacia delante.
+    fn project(&self, robot: &Point2<f64>) -> (usize, Point2<f64>) {
+        (self.segment..self.path.len() - 1)
+            .map(|i| (i, closest_on_segment(robot, &self.path[i], &self.path[i + 1])))
+            .min_by(|a, b| nalgebra::distance(robot, &a.1).total_cmp(&nalgebra::distance(robot, &b.1)))
+            .unwrap_or((self.segment, self.path[self.segment]))
+    }
+
+    fn point_along(&self, segment: usize, from: &Point2<f64>, mut distance: f64) -> Point2<f64> {
+        let mut current = *from;
+        for next in &self.path[segment + 1..] {
+            let length = nalgebra::distance(&current, next);
+            if length >= distance {
+                return current + (next - current) * (distance / length);
+            }
+            distance -= length;
+            current = *next;
+        }
+        current
+    }
+
+    fn distance_to_end(&self, segment: usize, from: &Point2<f64>) -> f64 {
+        let mut total = nalgebra::distance(from, &self.path[segment + 1]);
+        for pair in self.path[segment + 1..].windows(2) {
+            total += nalgebra::distance(&pair[0], &pair[1]);
+        }
+        total
+    }
+
+    fn segment_direction(&self, segment: usize) -> Vector2<f64> {
+        let direction = self.path[segment + 1] - self.path[segment];
+        if direction.norm() > 1e-9 { direction.normalize() } else { Vector2::x() }
+    }
+}
+
+fn closest_on_segment(point: &Point2<f64>, a: &Point2<f64>, b: &Point2<f64>) -> Point2<f64> {
+    let ab = b - a;
+    let length2 = ab.norm_squared();
+    if length2 < 1e-12 {
+        return *a;
+    }
+    let t = ((point - a).dot(&ab) / length2).clamp(0.0, 1.0);
+    a + ab * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Integra el robot diferencial siguiendo la ruta; devuelve el máximo error lateral.
+    fn drive(tracker: &mut PathTracker, start: Point3<f64>, yaw: f64, steps: usize) -> (Point3<f64>, f64, f64) {
+        let (mut position, mut yaw, mut speed) = (start, yaw, 0.0);
+        let mut max_error: f64 = 0.0;
+        let dt = 0.05;
+        for _ in 0..steps {
+            let Some(command) = tracker.compute(&position, yaw, speed, 1.0, true) else { break };
+            speed = command.linear;
+            yaw += command.angular * dt;
+            position += nalgebra::Vector3::new(yaw.cos(), yaw.sin(), 0.0) * speed * dt;
+            let (_, projection) = tracker.project(&position.xy());
+            max_error = max_error.max(nalgebra::distance(&projection, &position.xy()));
+        }
+        (position, max_error, speed)
+    }
+
+    fn corner_path() -> Vec<Point3<f64>> {
+        vec![Point3::new(14.0, -3.0, 0.0), Point3::new(14.0, 1.0, 0.0)]
+    }
+
+    #[test]
+    fn test_pure_pursuit_follows_corner_and_stops() {
+        let mut tracker = PathTracker::new(PathTrackerConfig::default(), 1.0, 1.5);
+        let start = Point3::new(10.0, -3.0, 0.0);
+        tracker.set_path(&start, &corner_path());
+
+        let (end, max_error, speed) = drive(&mut tracker, start, 0.0, 600);
+        assert!(nalgebra::distance(&end, &Point3::new(14.0, 1.0, 0.0)) < 0.1);
+        assert!(max_error < 0.5); // Recorta la esquina sin oscilar
+        assert!(speed < 0.2);
+        assert_eq!(tracker.remaining_waypoints(), 1);
+    }
+
+    #[test]
+    fn test_stanley_converges_to_path() {
+        let config = PathTrackerConfig { law: TrackingLaw::Stanley, ..PathTrackerConfig::default() };
+        let mut tracker = PathTracker::new(config, 1.0, 1.5);
+        let start = Point3::new(10.0, -3.0, 0.0);
+        tracker.set_path(&start, &corner_path());
+
+        // Arranca desplazado 0.5 m a la derecha de la ruta
+        let (end, _, _) = drive(&mut tracker, Point3::new(10.0, -3.5, 0.0), 0.0, 600);
+        assert!(nalgebra::distance(&end, &Point3::new(14.0, 1.0, 0.0)) < 0.15);
+    }
+
+    #[test]
+    fn test_curvature_speed_reduction_and_turn_in_place() {
+        let mut tracker = PathTracker::new(PathTrackerConfig::default(), 1.0, 1.5);
+        let start = Point3::new(0.0, 0.0, 0.0);
+        tracker.set_path(&start, &[Point3::new(10.0, 0.0, 0.0)]);
+
+        let straight = tracker.compute(&start, 0.0, 1.0, 1.0, false).unwrap();
+        assert!((straight.linear - 1.0).abs() < 1e-9 && straight.angular.abs() < 1e-9);
+
+        // Con rumbo desviado 45° la curvatura limita la velocidad
+        let turning = tracker.compute(&start, 45f64.to_radians(), 1.0, 1.0, false).unwrap();
+        assert!(turning.linear < 1.0 && turning.angular < 0.0);
+
+        // Ruta a la espalda: giro sin avance
+        let behind = tracker.compute(&start, std::f64::consts::PI, 0.0, 1.0, false).unwrap();
+        assert_eq!(behind.linear, 0.0);
+        assert!(behind.angular.abs() > 0.0);
+    }
+}
@@ -0,0 +1,384 @@
+// 🎯 Multi-Target Obstacle Tracking Module
+// File: projects/mechros2/src/navigation/tracking.rs
+
+use nalgebra::{Matrix2, Matrix2x4, Matrix4, Point3, Vector2, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+use super::{Obstacle, ObstacleType};
+
+// Coste asignado a pares fuera de la puerta de validación
+const GATED_COST: f64 = 1e6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Association {
+    Hungarian,        // Asignación óptima global (Kuhn-Munkres)
+    NearestNeighbour, // Vecino más cercano global, voraz
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackerConfig {
+    pub association: Association,
+    pub gate_distance: f64,     // Distancia máxima detección-predicción (m)
+    pub process_noise: f64,     // Densidad espectral de aceleración (m²/s³)
+    pub measurement_noise: f64, // Desviación típica de la posición medida (m)
+    pub initial_velocity_std: f64,
+    pub confirm_hits: u32,      // Actualizaciones necesarias para publicar el track
+    pub max_age: f64,           // Tiempo sin detecciones antes de eliminar el track (s)
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            association: Association::Hungarian,
+            gate_distance: 1.0,
+            process_noise: 1.0,
+            measurement_noise: 0.1,
+            initial_velocity_std: 2.0,
+            confirm_hits: 3,
+            max_age: 1.0,
+        }
+    }
+}
+
+/// Objetivo seguido con filtro de Kalman de velocidad constante: estado [x, y, vx, vy].
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub id: u64,
+    state: Vector4<f64>,
+    covariance: Matrix4<f64>,
+    height: f64,
+    pub size: Vector3<f64>,
+    pub obstacle_type: ObstacleType,
+    pub confidence: f32,
+    pub hits: u32,
+    pub last_update: chrono::DateTime<chrono::Utc>,
+}
+
+impl Track {
+    pub fn position(&self) -> Point3<f64> {
+        Point3::new(self.state[0], self.state[1], self.height)
+    }
+
+    pub fn velocity(&self) -> Vector3<f64> {
+        Vector3::new(self.state[2], self.state[3], 0.0)
+    }
+
+    /// Desviación típica de la velocidad estimada (m/s), máximo de ambos ejes.
+    pub fn velocity_std(&self) -> f64 {
+        self.covariance[(2, 2)].max(self.covariance[(3, 3)]).sqrt()
+    }
+
+    fn predict(&mut self, dt: f64, process_noise: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let mut f = Matrix4::identity();
+        f[(0, 2)] = dt;
+        f[(1, 3)] = dt;
+
+        // Ruido de aceleración blanca discretizado
+        let (dt2, dt3) = (dt * dt / 2.0, dt * dt * dt / 3.0);
+        let mut q = Matrix4::zeros();
+        for axis in 0..2 {
+            q[(axis, axis)] = dt3;
+            q[(axis, axis + 2)] = dt2;
+            q[(axis + 2, axis)] = dt2;
+            q[(axis + 2, axis + 2)] = dt;
+        }
+
+        self.state = f * self.state;
+        self.covariance = f * self.covariance * f.transpose() + q * process_noise;
+    }
+
+    fn correct(&mut self, measurement: &Vector2<f64>, measurement_noise: f64) {
+        let h = Matrix2x4::new(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+        );
+        let innovation = measurement - h * self.state;
+        let s = h * self.covariance * h.transpose() + Matrix2::identity() * measurement_noise.powi(2);
+        let Some(s_inv) = s.try_inverse() else { return };
+        let gain = self.covariance * h.transpose() * s_inv;
+
+        self.state += gain * innovation;
+        self.covariance = (Matrix4::identity() - gain * h) * self.covariance;
+    }
+}
+
+/// Asocia detecciones entre fotogramas y mantiene un track por objetivo.
+pub struct ObstacleTracker {
+    config: TrackerConfig,
+    tracks: Vec<Track>,
+    next_id: u64,
+}
+
+impl ObstacleTracker {
+    pub fn new(config: TrackerConfig) -> Self {
+        Self { config, tracks: Vec::new(), next_id: 1 }
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Integra las detecciones de un fotograma (marco mundo): predice, asocia,
+    /// corrige los tracks emparejados, crea tracks nuevos y elimina los caducados.
+    pub fn update(&mut self, detections: &[Obstacle], timestamp: chrono::DateTime<chrono::Utc>) {
+        for track in &mut self.tracks {
+            track.predict(seconds(timestamp - track.last_update), self.config.process_noise);
+        }
+
+        let cost: Vec<Vec<f64>> = self.tracks.iter()
+            .map(|track| detections.iter().map(|detection| self.cost(track, detection)).collect())
+            .collect();
+        let assignment = match self.config.association {
+            Association::Hungarian => hungarian(&cost, detections.len()),
+            Association::NearestNeighbour => nearest_neighbour(&cost, detections.len()),
+        };
+
+        let mut matched = vec![false; detections.len()];
+        for (track, detection) in self.tracks.iter_mut().zip(assignment) {
+            let Some(index) = detection else { continue };
+            let detection = &detections[index];
+            matched[index] = true;
+
+            track.correct(&detection.position.coords.xy(), self.config.measurement_noise);
+            track.height = detection.position.z;
+            track.size = detection.size;
+            track.confidence = detection.confidence;
+            if track.obstacle_type == ObstacleType::Unknown {
+                track.obstacle_type = detection.obstacle_type.clone();
+            }
+            track.hits += 1;
+            track.last_update = timestamp;
+        }
+
+        for (detection, _) in detections.iter().zip(&matched).filter(|(_, matched)| !**matched) {
+            let velocity_var = self.config.initial_velocity_std.powi(2);
+            self.tracks.push(Track {
+                id: self.next_id,
+                state: Vector4::new(detection.position.x, detection.position.y, 0.0, 0.0),
+                covariance: Matrix4::from_diagonal(&Vector4::new(
+                    self.config.measurement_noise.powi(2),
+                    self.config.measurement_noise.powi(2),
+                    velocity_var,
+                    velocity_var,
+                )),
+                height: detection.position.z,
+                size: detection.size,
+                obstacle_type: detection.obstacle_type.clone(),
+                confidence: detection.confidence,
+                hits: 1,
+                last_update: timestamp,
+            });
+            self.next_id += 1;
+        }
+
+        self.prune(timestamp);
+    }
+
+    /// Elimina individualmente los tracks sin detecciones durante `max_age`.
+    pub fn prune(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        let max_age = self.config.max_age;
+        self.tracks.retain(|track| seconds(now - track.last_update) <= max_age);
+    }
+
+    /// Tracks confirmados como obstáculos con su velocidad estimada.
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        self.tracks.iter()
+            .filter(|track| track.hits >= self.config.confirm_hits)
+            .map(|track| Obstacle {
+                position: track.position(),
+                size: track.size,
+                yaw: 0.0,
+                velocity: Some(track.velocity()),
+                obstacle_type: track.obstacle_type.clone(),
+                confidence: track.confidence,
+                source: super::ObstacleSource::Vision,
+            })
+            .collect()
+    }
+
+    fn cost(&self, track: &Track, detection: &Obstacle) -> f64 {
+        // Clases conocidas distintas no se asocian
+        let compatible = track.obstacle_type == detection.obstacle_type
+            || track.obstacle_type == ObstacleType::Unknown
+            || detection.obstacle_type == ObstacleType::Unknown;
+        let distance = (track.position().xy() - detection.position.xy()).norm();
+        if compatible && distance <= self.config.gate_distance { distance } else { GATED_COST }
+    }
+}
+
+/// Asignación de coste mínimo (Kuhn-Munkres con potenciales). Devuelve, para
+/// cada fila, la columna asignada; los pares fuera de puerta quedan sin asignar.
+fn hungarian(cost: &[Vec<f64>], columns: usize) -> Vec<Option<usize>> {
+    let rows = cost.len();
+    let n = rows.max(columns);
+    if n == 0 {
+        return Vec::new();
+    }
+    // Matriz cuadrada, rellenando con coste de puerta
+    let at = |i: usize, j: usize| if i < rows && j < columns { cost[i][j] } else { GATED_COST };
+
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut p = vec![0usize; n + 1]; // Fila (1-indexada) asignada a cada columna
+    let mut way = vec![0usize; n + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=n {
+                if !used[j] {
+                    let reduced = at(i0 - 1, j - 1) - u[i0] - v[j];
+                    if reduced < min_v[j] {
+                        min_v[j] = reduced;
+                        way[j] = j0;
+                    }
+                    if min_v[j] < delta {
+                        delta = min_v[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for j in 1..=n {
+        let i = p[j];
+        if i >= 1 && i <= rows && j <= columns && cost[i - 1][j - 1] < GATED_COST {
+            assignment[i - 1] = Some(j - 1);
+        }
+    }
+    assignment
+}
+
+/// Empareja primero los pares más cercanos de todo el conjunto.
+fn nearest_neighbour(cost: &[Vec<f64>], columns: usize) -> Vec<Option<usize>> {
+    let mut pairs: Vec<(f64, usize, usize)> = cost.iter().enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &c)| (c, i, j)))
+        .filter(|(c, _, _)| *c < GATED_COST)
+        .collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut assignment = vec![None; cost.len()];
+    let mut taken = vec![false; columns];
+    for (_, i, j) in pairs {
+        if assignment[i].is_none() && !taken[j] {
+            assignment[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+    assignment
+}
+
+fn seconds(duration: chrono::Duration) -> f64 {
+    duration.num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ObstacleSource;
+
+    fn detection(x: f64, y: f64, obstacle_type: ObstacleType) -> Obstacle {
+        Obstacle {
+            position: Point3::new(x, y, 0.0),
+            size: Vector3::new(0.5, 0.5, 1.0),
+            yaw: 0.0,
+            velocity: None,
+            obstacle_type,
+            confidence: 0.9,
+            source: ObstacleSource::Vision,
+        }
+    }
+
+    fn at(ms: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn test_persistent_ids_and_velocity() {
+        let mut tracker = ObstacleTracker::new(TrackerConfig::default());
+
+        for frame in 0..30 {
+            let t = frame as f64 * 0.1;
+            let mut detections = vec![
+                detection(2.0 + 0.8 * t, 1.0, ObstacleType::Person),
+                detection(5.0, 3.0 - 0.5 * t, ObstacleType::Vehicle),
+            ];
+            if frame % 2 == 1 {
+                detections.reverse(); // El orden de las detecciones no importa
+            }
+            tracker.update(&detections, at(frame * 100));
+        }
+
+        assert_eq!(tracker.tracks().len(), 2);
+        let person = tracker.tracks().iter().find(|t| t.obstacle_type == ObstacleType::Person).unwrap();
+        let vehicle = tracker.tracks().iter().find(|t| t.obstacle_type == ObstacleType::Vehicle).unwrap();
+        assert_eq!((person.id, vehicle.id), (1, 2));
+        assert!((person.velocity() - Vector3::new(0.8, 0.0, 0.0)).norm() < 0.05);
+        assert!((vehicle.velocity() - Vector3::new(0.0, -0.5, 0.0)).norm() < 0.05);
+        assert_eq!(tracker.obstacles().len(), 2);
+    }
+
+    #[test]
+    fn test_tracks_age_out_individually() {
+        let mut tracker = ObstacleTracker::new(TrackerConfig::default());
+        tracker.update(&[detection(0.0, 0.0, ObstacleType::Unknown), detection(4.0, 0.0, ObstacleType::Unknown)], at(0));
+
+        // Solo se sigue viendo el segundo objetivo
+        for frame in 1..=15 {
+            tracker.update(&[detection(4.0, 0.0, ObstacleType::Unknown)], at(frame * 100));
+        }
+        let ids: Vec<u64> = tracker.tracks().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2]);
+
+        // Una detección lejana abre un track nuevo en lugar de saltar el existente
+        tracker.update(&[detection(4.0, 0.0, ObstacleType::Unknown), detection(9.0, 9.0, ObstacleType::Unknown)], at(1600));
+        assert_eq!(tracker.tracks().iter().map(|t| t.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(tracker.obstacles().len(), 1); // El nuevo aún no está confirmado
+    }
+
+    #[test]
+    fn test_hungarian_beats_greedy_assignment() {
+        // Tracks en x=0 y x=1; detecciones en x=0.9 y x=1.85 con puerta de 1 m
+        let cost = vec![
+            vec![0.9, GATED_COST],
+            vec![0.1, 0.85],
+        ];
+        assert_eq!(hungarian(&cost, 2), vec![Some(0), Some(1)]);
+        assert_eq!(nearest_neighbour(&cost, 2), vec![None, Some(0)]);
+
+        // Más detecciones que tracks y viceversa
+        assert_eq!(hungarian(&[vec![0.5, 0.2, 0.7]], 3), vec![Some(1)]);
+        assert_eq!(hungarian(&[vec![0.3], vec![0.1]], 1), vec![None, Some(0)]);
+    }
+}
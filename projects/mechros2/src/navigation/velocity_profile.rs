@@ -0,0 +1,258 @@
+// 📈 Velocity Profile Module
+// File: projects/mechros2/src/navigation/velocity_profile.rs
+
+use nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+use super::{Waypoint, WaypointType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProfileShape {
+    Trapezoidal, // Aceleración constante
+    JerkLimited, // Curva en S: la aceleración cambia con sobreaceleración acotada
+}
+
+#[derive(Debug, Clone)]
+pub struct VelocityProfileConfig {
+    pub shape: ProfileShape,
+    pub max_linear_accel: f64,  // m/s²
+    pub max_linear_jerk: f64,   // m/s³
+    pub max_angular_accel: f64, // rad/s²
+    pub max_lateral_accel: f64, // En las esquinas: v ≤ √(a·R) (m/s²)
+    pub precision_speed: f64,   // Velocidad de paso por waypoints de precisión (m/s)
+}
+
+impl Default for VelocityProfileConfig {
+    fn default() -> Self {
+        Self {
+            shape: ProfileShape::Trapezoidal,
+            max_linear_accel: 0.8,
+            max_linear_jerk: 2.0,
+            max_angular_accel: 2.0,
+            max_lateral_accel: 0.6,
+            precision_speed: 0.2,
+        }
+    }
+}
+
+/// Parametriza en el tiempo una ruta poligonal: velocidad de paso por cada
+/// waypoint y tiempo de cada tramo.
+pub struct VelocityProfiler {
+    config: VelocityProfileConfig,
+    max_linear_speed: f64,
+    max_angular_speed: f64,
+}
+
+impl VelocityProfiler {
+    pub fn new(config: VelocityProfileConfig, max_linear_speed: f64, max_angular_speed: f64) -> Self {
+        Self { config, max_linear_speed, max_angular_speed }
+    }
+
+    pub fn config(&self) -> &VelocityProfileConfig {
+        &self.config
+    }
+
+    /// Rellena `planned_speed` y `segment_time` de cada waypoint partiendo de
+    /// `start` en reposo y devuelve el tiempo total estimado.
+    pub fn apply(&self, start: &Point3<f64>, waypoints: &mut [Waypoint]) -> f64 {
+        let n = waypoints.len();
+        if n == 0 {
+            return 0.0;
+        }
+        let points: Vec<Point3<f64>> = std::iter::once(*start).chain(waypoints.iter().map(|w| w.position)).collect();
+        let lengths: Vec<f64> = points.windows(2).map(|w| nalgebra::distance(&w[0], &w[1])).collect();
+        let caps: Vec<f64> = waypoints.iter().map(|w| w.max_speed.min(self.max_linear_speed)).collect();
+        let turns: Vec<f64> = (0..=n).map(|k| turn_angle(&points, k)).collect();
+
+        // Límite de velocidad en cada vértice (0 = partida)
+        let mut speeds: Vec<f64> = (0..=n)
+            .map(|k| {
+                if k == 0 {
+                    return 0.0;
+                }
+                let waypoint = &waypoints[k - 1];
+                let mut limit = caps[k - 1];
+                if k < n {
+                    limit = limit.min(caps[k]);
+                    limit = limit.min(self.corner_speed(turns[k], lengths[k - 1].min(lengths[k])));
+                }
+                match waypoint.waypoint_type {
+                    WaypointType::Stop => 0.0,
+                    WaypointType::Precision => limit.min(self.config.precision_speed),
+                    _ if k == n => 0.0, // La ruta siempre termina parada
+                    _ => limit,
+                }
+            })
+            .collect();
+
+        // Pasadas hacia delante (aceleración) y hacia atrás (frenada)
+        for k in 1..=n {
+            speeds[k] = speeds[k].min(self.reachable(speeds[k - 1], lengths[k - 1], caps[k - 1]));
+        }
+        for k in (0..n).rev() {
+            speeds[k] = speeds[k].min(self.reachable(speeds[k + 1], lengths[k], caps[k]));
+        }
+
+        let mut total = 0.0;
+        for k in 1..=n {
+            let mut time = self.segment_time(speeds[k - 1], speeds[k], caps[k - 1], lengths[k - 1]);
+            // Parado en una esquina: giro sobre sí mismo antes del tramo
+            if k > 1 && speeds[k - 1] < 1e-6 {
+                time += self.turn_time(turns[k - 1]);
+            }
+            waypoints[k - 1].planned_speed = speeds[k];
+            waypoints[k - 1].segment_time = time;
+            total += time;
+        }
+        total
+    }
+
+    /// Velocidad máxima en una esquina redondeada con tangente igual a medio tramo.
+    fn corner_speed(&self, turn: f64, shortest: f64) -> f64 {
+        if turn < 1e-6 {
+            return f64::INFINITY;
+        }
+        if turn > std::f64::consts::PI - 1e-3 {
+            return 0.0;
+        }
+        let radius = 0.5 * shortest / (turn / 2.0).tan();
+        (self.config.max_lateral_accel * radius).sqrt().min(self.max_angular_speed * radius)
+    }
+
+    /// Distancia y tiempo para pasar de `v0` a `v1`.
+    fn ramp(&self, v0: f64, v1: f64) -> (f64, f64) {
+        let dv = (v1 - v0).abs();
+        let a = self.config.max_linear_accel;
+        let time = match self.config.shape {
+            ProfileShape::Trapezoidal => dv / a,
+            ProfileShape::JerkLimited => {
+                let j = self.config.max_linear_jerk;
+                if dv >= a * a / j { dv / a + a / j } else { 2.0 * (dv / j).sqrt() }
+            }
+        };
+        // Perfil simétrico: la velocidad media es la media de los extremos
+        (0.5 * (v0 + v1) * time, time)
+    }
+
+    /// Mayor velocidad alcanzable desde `v0` en `length` metros, sin superar `cap`.
+    fn reachable(&self, v0: f64, length: f64, cap: f64) -> f64 {
+        if cap <= v0 {
+            return cap;
+        }
+        match self.config.shape {
+            ProfileShape::Trapezoidal => (v0 * v0 + 2.0 * self.config.max_linear_accel * length).sqrt().min(cap),
+            ProfileShape::JerkLimited => bisect(v0, cap, |v| self.ramp(v0, v).0 <= length),
+        }
+    }
+
+    fn segment_time(&self, v0: f64, v1: f64, cap: f64, length: f64) -> f64 {
+        if length < 1e-9 {
+            return self.ramp(v0, v1).1;
+        }
+        let fits = |peak: f64| self.ramp(v0, peak).0 + self.ramp(peak, v1).0 <= length;
+        let floor = v0.max(v1);
+        let peak = if fits(cap) { cap } else { bisect(floor, cap.max(floor), fits) };
+        let (d1, t1) = self.ramp(v0, peak);
+        let (d2, t2) = self.ramp(peak, v1);
+        let cruise = (length - d1 - d2).max(0.0);
+        t1 + t2 + if peak > 1e-9 { cruise / peak } else { 0.0 }
+    }
+
+    fn turn_time(&self, angle: f64) -> f64 {
+        let (w, alpha) = (self.max_angular_speed, self.config.max_angular_accel);
+        if angle >= w * w / alpha { angle / w + w / alpha } else { 2.0 * (angle / alpha).sqrt() }
+    }
+}
+
+/// Mayor valor en [lo, hi] que cumple `ok`, suponiendo `ok` monótona decreciente.
+fn bisect(mut lo: f64, mut hi: f64, ok: impl Fn(f64) -> bool) -> f64 {
+    for _ in 0..50 {
+        let mid = 0.5 * (lo + hi);
+        if ok(mid) { lo = mid } else { hi = mid }
+    }
+    lo
+}
+
+/// Cambio de rumbo absoluto en el vértice `k` (0 en los extremos).
+fn turn_angle(points: &[Point3<f64>], k: usize) -> f64 {
+    if k == 0 || k + 1 >= points.len() {
+        return 0.0;
+    }
+    let (a, b) = (points[k] - points[k - 1], points[k + 1] - points[k]);
+    if a.xy().norm() < 1e-9 || b.xy().norm() < 1e-9 {
+        return 0.0;
+    }
+    let angle = b.y.atan2(b.x) - a.y.atan2(a.x);
+    ((angle + std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI) - std::f64::consts::PI).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoint(x: f64, y: f64, waypoint_type: WaypointType) -> Waypoint {
+        Waypoint {
+            position: Point3::new(x, y, 0.0),
+            tolerance: 0.1,
+            max_speed: 1.0,
+            waypoint_type,
+            planned_speed: 0.0,
+            segment_time: 0.0,
+        }
+    }
+
+    fn profiler(shape: ProfileShape) -> VelocityProfiler {
+        VelocityProfiler::new(VelocityProfileConfig { shape, max_linear_accel: 0.5, ..VelocityProfileConfig::default() }, 1.0, 1.5)
+    }
+
+    #[test]
+    fn test_trapezoid_time_on_straight_line() {
+        // 10 m con a = 0.5 y v = 1: 2 s acelerando (1 m), 8 m de crucero, 2 s frenando
+        let mut waypoints = vec![waypoint(5.0, 0.0, WaypointType::Transit), waypoint(10.0, 0.0, WaypointType::Stop)];
+        let total = profiler(ProfileShape::Trapezoidal).apply(&Point3::origin(), &mut waypoints);
+        assert!((total - 12.0).abs() < 1e-9);
+        assert!((waypoints[0].planned_speed - 1.0).abs() < 1e-9);
+        assert_eq!(waypoints[1].planned_speed, 0.0);
+        assert!((waypoints[0].segment_time - 6.0).abs() < 1e-9);
+
+        // Tramo corto: perfil triangular sin alcanzar la velocidad máxima
+        let mut short = vec![waypoint(1.0, 0.0, WaypointType::Stop)];
+        let total = profiler(ProfileShape::Trapezoidal).apply(&Point3::origin(), &mut short);
+        assert!((total - 2.0 * (1.0f64 / 0.5).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_slows_for_corners_and_precision() {
+        let start = Point3::new(-1.0, 2.0, 0.0);
+        let mut waypoints = vec![
+            waypoint(1.0, 2.0, WaypointType::Transit),   // Esquina de 90°
+            waypoint(1.0, 6.0, WaypointType::Transit),   // Recto
+            waypoint(1.0, 10.0, WaypointType::Precision),
+            waypoint(1.0, 14.0, WaypointType::Stop),
+        ];
+        let profiler = profiler(ProfileShape::Trapezoidal);
+        profiler.apply(&start, &mut waypoints);
+
+        let corner = 0.6f64.sqrt(); // R = 1 m con tangente de medio tramo corto
+        assert!((waypoints[0].planned_speed - corner).abs() < 1e-9);
+        assert!((waypoints[1].planned_speed - 1.0).abs() < 1e-9);
+        assert!((waypoints[2].planned_speed - 0.2).abs() < 1e-9);
+
+        // Giro de 180°: parada y rotación sobre sí mismo incluidas en el tiempo
+        let mut reverse = vec![waypoint(2.0, 0.0, WaypointType::Transit), waypoint(0.0, 0.0, WaypointType::Stop)];
+        profiler.apply(&Point3::origin(), &mut reverse);
+        assert_eq!(reverse[0].planned_speed, 0.0);
+        assert!(reverse[1].segment_time > reverse[0].segment_time);
+    }
+
+    #[test]
+    fn test_jerk_limited_is_slower_than_trapezoid() {
+        let make = || vec![waypoint(4.0, 0.0, WaypointType::Transit), waypoint(8.0, 0.0, WaypointType::Stop)];
+        let (mut trapezoid, mut s_curve) = (make(), make());
+        let t_trapezoid = profiler(ProfileShape::Trapezoidal).apply(&Point3::origin(), &mut trapezoid);
+        let t_s_curve = profiler(ProfileShape::JerkLimited).apply(&Point3::origin(), &mut s_curve);
+
+        // Con a = 0.5 y j = 2 cada rampa 0→1 tarda 2.25 s en lugar de 2 s
+        assert!((t_s_curve - t_trapezoid - 0.25).abs() < 1e-6);
+        assert!((s_curve[0].planned_speed - 1.0).abs() < 1e-9);
+    }
+}
@@ -5,11 +5,17 @@ use r2r::QosProfile;
 use serde_json;
 use tracing::{info, debug, error};
 use crate::SystemState;
+use crate::sensors::battery::{BatteryEstimate, ChargeState};
+use crate::sensors::telemetry::TelemetryPayload;
+use crate::navigation::occupancy_grid::OccupancyGrid;
 
 pub struct MechNodeManager {
     ctx: r2r::Context,
     node: r2r::Node,
     publishers: HashMap<String, r2r::Publisher<r2r::std_msgs::msg::String>>,
+    battery_publisher: Option<r2r::Publisher<r2r::sensor_msgs::msg::BatteryState>>,
+    binary_telemetry_publisher: Option<r2r::Publisher<r2r::std_msgs::msg::UInt8MultiArray>>,
+    map_publisher: Option<r2r::Publisher<r2r::nav_msgs::msg::OccupancyGrid>>,
     subscribers: HashMap<String, Arc<tokio::sync::Mutex<Vec<String>>>>,
 }
 
@@ -24,6 +30,9 @@ impl MechNodeManager {
             ctx,
             node,
             publishers: HashMap::new(),
+            battery_publisher: None,
+            binary_telemetry_publisher: None,
+            map_publisher: None,
             subscribers: HashMap::new(),
         };
 
@@ -56,6 +65,46 @@ impl MechNodeManager {
         )?;
         self.publishers.insert("telemetry".to_string(), telemetry_pub);
 
+        // Publisher para telemetría binaria (CBOR/MessagePack); el tipo de
+        // contenido viaja en la etiqueta de la primera dimensión del layout
+        self.binary_telemetry_publisher = Some(self.node.create_publisher::<r2r::std_msgs::msg::UInt8MultiArray>(
+            "/mechros2/telemetry/binary",
+            QosProfile::default()
+        )?);
+
+        // Publisher para eventos de salud de sensores
+        let sensor_events_pub = self.node.create_publisher::<r2r::std_msgs::msg::String>(
+            "/mechros2/sensor_events",
+            QosProfile::default()
+        )?;
+        self.publishers.insert("sensor_events".to_string(), sensor_events_pub);
+
+        // Publisher para alarmas ambientales
+        let environment_alarms_pub = self.node.create_publisher::<r2r::std_msgs::msg::String>(
+            "/mechros2/environment_alarms",
+            QosProfile::default()
+        )?;
+        self.publishers.insert("environment_alarms".to_string(), environment_alarms_pub);
+
+        // Publisher para incidentes de estabilidad (vuelco, caída, colisión)
+        let incidents_pub = self.node.create_publisher::<r2r::std_msgs::msg::String>(
+            "/mechros2/incidents",
+            QosProfile::default()
+        )?;
+        self.publishers.insert("incidents".to_string(), incidents_pub);
+
+        // Publisher para estado de batería (sensor_msgs/BatteryState)
+        self.battery_publisher = Some(self.node.create_publisher::<r2r::sensor_msgs::msg::BatteryState>(
+            "/mechros2/battery_state",
+            QosProfile::default()
+        )?);
+
+        // Publisher para el mapa de ocupación (nav_msgs/OccupancyGrid)
+        self.map_publisher = Some(self.node.create_publisher::<r2r::nav_msgs::msg::OccupancyGrid>(
+            "/mechros2/map",
+            QosProfile::default()
+        )?);
+
         // Subscriber para objetivos de navegación
         let goal_buffer = Arc::new(tokio::sync::Mutex::new(Vec::new()));
         let goal_sub = self.node.create_subscription::<r2r::std_msgs::msg::String>(
@@ -118,14 +167,144 @@ impl MechNodeManager {
         Ok(())
     }
 
-    pub async fn publish_telemetry(&self, data: &str) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(publisher) = self.publishers.get("telemetry") {
+    pub async fn publish_telemetry(&self, payload: &TelemetryPayload) -> Result<(), Box<dyn std::error::Error>> {
+        use r2r::std_msgs::msg::{MultiArrayDimension, MultiArrayLayout, UInt8MultiArray};
+
+        if payload.content_type == "application/json" {
+            if let Some(publisher) = self.publishers.get("telemetry") {
+                let msg = r2r::std_msgs::msg::String {
+                    data: String::from_utf8(payload.bytes.clone())?,
+                };
+
+                publisher.publish(&msg)?;
+                debug!("📈 Telemetría publicada ({} bytes)", payload.bytes.len());
+            }
+        } else if let Some(publisher) = &self.binary_telemetry_publisher {
+            let msg = UInt8MultiArray {
+                layout: MultiArrayLayout {
+                    dim: vec![MultiArrayDimension {
+                        label: payload.content_type.to_string(),
+                        size: payload.bytes.len() as u32,
+                        stride: payload.bytes.len() as u32,
+                    }],
+                    data_offset: 0,
+                },
+                data: payload.bytes.clone(),
+            };
+
+            publisher.publish(&msg)?;
+            debug!("📈 Telemetría publicada ({}, {} bytes)", payload.content_type, payload.bytes.len());
+        }
+        Ok(())
+    }
+
+    pub async fn publish_sensor_event(&self, data: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(publisher) = self.publishers.get("sensor_events") {
             let msg = r2r::std_msgs::msg::String {
                 data: data.to_string(),
             };
 
             publisher.publish(&msg)?;
-            debug!("📈 Telemetría publicada");
+            debug!("🩺 Evento de sensor publicado");
+        }
+        Ok(())
+    }
+
+    pub async fn publish_environment_alarm(&self, data: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(publisher) = self.publishers.get("environment_alarms") {
+            let msg = r2r::std_msgs::msg::String {
+                data: data.to_string(),
+            };
+
+            publisher.publish(&msg)?;
+            debug!("🌡️ Alarma ambiental publicada");
+        }
+        Ok(())
+    }
+
+    pub async fn publish_incident(&self, data: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(publisher) = self.publishers.get("incidents") {
+            let msg = r2r::std_msgs::msg::String {
+                data: data.to_string(),
+            };
+
+            publisher.publish(&msg)?;
+            debug!("🚨 Incidente publicado");
+        }
+        Ok(())
+    }
+
+    pub async fn publish_battery_state(&self, battery: &BatteryEstimate) -> Result<(), Box<dyn std::error::Error>> {
+        use r2r::sensor_msgs::msg::BatteryState;
+
+        if let Some(publisher) = &self.battery_publisher {
+            let now = chrono::Utc::now();
+            let msg = BatteryState {
+                header: r2r::std_msgs::msg::Header {
+                    stamp: r2r::builtin_interfaces::msg::Time {
+                        sec: now.timestamp() as i32,
+                        nanosec: now.timestamp_subsec_nanos(),
+                    },
+                    frame_id: "base_link".to_string(),
+                },
+                voltage: battery.voltage as f32,
+                temperature: battery.temperature.unwrap_or(f32::NAN),
+                current: battery.current as f32,
+                charge: battery.charge_ah as f32,
+                capacity: battery.capacity_ah as f32,
+                design_capacity: battery.capacity_ah as f32,
+                percentage: (battery.state_of_charge / 100.0) as f32,
+                power_supply_status: match battery.charge_state {
+                    ChargeState::Charging => BatteryState::POWER_SUPPLY_STATUS_CHARGING,
+                    ChargeState::Discharging => BatteryState::POWER_SUPPLY_STATUS_DISCHARGING,
+                    ChargeState::Idle => BatteryState::POWER_SUPPLY_STATUS_NOT_CHARGING,
+                    ChargeState::Full => BatteryState::POWER_SUPPLY_STATUS_FULL,
+                },
+                power_supply_health: BatteryState::POWER_SUPPLY_HEALTH_GOOD,
+                power_supply_technology: BatteryState::POWER_SUPPLY_TECHNOLOGY_LION,
+                present: true,
+                ..Default::default()
+            };
+
+            publisher.publish(&msg)?;
+            debug!("🔋 Estado de batería publicado");
+        }
+        Ok(())
+    }
+
+    pub async fn publish_occupancy_grid(&self, grid: &OccupancyGrid) -> Result<(), Box<dyn std::error::Error>> {
+        use r2r::nav_msgs::msg::{MapMetaData, OccupancyGrid as OccupancyGridMsg};
+
+        if let Some(publisher) = &self.map_publisher {
+            let now = chrono::Utc::now();
+            let stamp = r2r::builtin_interfaces::msg::Time {
+                sec: now.timestamp() as i32,
+                nanosec: now.timestamp_subsec_nanos(),
+            };
+            let msg = OccupancyGridMsg {
+                header: r2r::std_msgs::msg::Header {
+                    stamp: stamp.clone(),
+                    frame_id: "map".to_string(),
+                },
+                info: MapMetaData {
+                    map_load_time: stamp,
+                    resolution: grid.resolution() as f32,
+                    width: grid.width() as u32,
+                    height: grid.height() as u32,
+                    origin: r2r::geometry_msgs::msg::Pose {
+                        position: r2r::geometry_msgs::msg::Point {
+                            x: grid.origin().x,
+                            y: grid.origin().y,
+                            z: 0.0,
+                        },
+                        orientation: r2r::geometry_msgs::msg::Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+                    },
+                },
+                data: grid.to_ros_data(),
+            };
+
+            publisher.publish(&msg)?;
+            debug!("🧱 Mapa de ocupación publicado ({}x{})", grid.width(), grid.height());
         }
         Ok(())
     }
@@ -160,6 +339,23 @@ impl MechNodeManager {
         }
     }
 
+    /// Extrae del búfer los comandos remotos cuyo campo `command` está en `names`;
+    /// el resto queda para otros consumidores.
+    pub async fn take_remote_commands(&self, names: &[&str]) -> Vec<String> {
+        let Some(buffer) = self.subscribers.get("remote_commands") else {
+            return Vec::new();
+        };
+        let mut msgs = buffer.lock().await;
+        let (taken, kept): (Vec<String>, Vec<String>) = msgs.drain(..).partition(|msg| {
+            serde_json::from_str::<serde_json::Value>(msg)
+                .ok()
+                .and_then(|value| value["command"].as_str().map(|name| names.contains(&name)))
+                .unwrap_or(false)
+        });
+        *msgs = kept;
+        taken
+    }
+
     pub async fn create_custom_publisher<T>(&mut self, topic: &str) -> Result<(), Box<dyn std::error::Error>>
     where
         T: r2r::WrappedTypesupport,
@@ -0,0 +1,186 @@
+// ⏱️ Sensor Acquisition Module
+// File: projects/mechros2/src/sensors/acquisition.rs
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use super::health::SensorKind;
+
+#[derive(Debug, Clone)]
+pub struct AcquisitionConfig {
+    /// Frecuencia nativa de muestreo por sensor (Hz).
+    pub rates: HashMap<SensorKind, f64>,
+    /// Muestras conservadas por sensor.
+    pub buffer_capacity: usize,
+}
+
+impl AcquisitionConfig {
+    pub fn rate(&self, kind: SensorKind) -> f64 {
+        self.rates.get(&kind).copied().unwrap_or(20.0)
+    }
+
+    pub fn period(&self, kind: SensorKind) -> Duration {
+        Duration::from_secs_f64(1.0 / self.rate(kind).max(0.1))
+    }
+}
+
+impl Default for AcquisitionConfig {
+    fn default() -> Self {
+        Self {
+            rates: HashMap::from([
+                (SensorKind::Imu, 100.0),
+                (SensorKind::Gps, 10.0),
+                (SensorKind::Lidar, 10.0),
+                (SensorKind::Environmental, 1.0),
+                (SensorKind::Proximity, 20.0),
+                (SensorKind::Battery, 1.0),
+                (SensorKind::WheelEncoders, 50.0),
+            ]),
+            buffer_capacity: 64,
+        }
+    }
+}
+
+/// Datos que pueden traer su propia marca de tiempo del hardware (p. ej. la hora del fix GPS).
+pub trait Stamped {
+    fn hardware_timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        None
+    }
+}
+
+/// Muestra de un sensor con su instante de adquisición.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample<T> {
+    /// Instante de la medida: el del hardware si existe; si no, el punto medio de la lectura.
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub hardware_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub received_at: chrono::DateTime<chrono::Utc>,
+    pub sequence: u64,
+    pub latency: Duration, // Duración de la lectura
+    pub value: T,
+}
+
+/// Estadísticas de latencia de lectura de un sensor, en milisegundos.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub last_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub samples: u64,
+}
+
+impl LatencyStats {
+    const SMOOTHING: f64 = 0.1;
+
+    fn record(&mut self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        self.mean_ms = if self.samples == 0 { ms } else { self.mean_ms + Self::SMOOTHING * (ms - self.mean_ms) };
+        self.last_ms = ms;
+        self.max_ms = self.max_ms.max(ms);
+        self.samples += 1;
+    }
+}
+
+struct Buffer<T> {
+    samples: VecDeque<Sample<T>>,
+    capacity: usize,
+    next_sequence: u64,
+    latency: LatencyStats,
+}
+
+/// Almacén circular de las últimas muestras de un sensor. Los bloqueos son
+/// breves y nunca se mantienen a través de un `await`, de modo que leer una
+/// instantánea no espera a ninguna adquisición en curso.
+pub struct SampleStore<T> {
+    inner: Mutex<Buffer<T>>,
+}
+
+impl<T: Clone> SampleStore<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Buffer {
+                samples: VecDeque::with_capacity(capacity),
+                capacity: capacity.max(1),
+                next_sequence: 0,
+                latency: LatencyStats::default(),
+            }),
+        }
+    }
+
+    pub fn push(
+        &self,
+        value: T,
+        hardware_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+        received_at: chrono::DateTime<chrono::Utc>,
+        latency: Duration,
+    ) {
+        let timestamp = hardware_timestamp.unwrap_or_else(|| {
+            received_at - chrono::Duration::from_std(latency / 2).unwrap_or_else(|_| chrono::Duration::zero())
+        });
+
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let sequence = inner.next_sequence;
+        inner.next_sequence += 1;
+        inner.latency.record(latency);
+
+        if inner.samples.len() == inner.capacity {
+            inner.samples.pop_front();
+        }
+        inner.samples.push_back(Sample {
+            timestamp,
+            hardware_timestamp,
+            received_at,
+            sequence,
+            latency,
+            value,
+        });
+    }
+
+    pub fn latest(&self) -> Option<Sample<T>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).samples.back().cloned()
+    }
+
+    /// Muestras almacenadas, de la más antigua a la más reciente.
+    pub fn history(&self) -> Vec<Sample<T>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).samples.iter().cloned().collect()
+    }
+
+    pub fn latency(&self) -> LatencyStats {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).latency.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer_keeps_latest() {
+        let store = SampleStore::new(3);
+        let now = chrono::Utc::now();
+        for i in 0..5 {
+            store.push(i, None, now, Duration::from_millis(2));
+        }
+
+        let history: Vec<i32> = store.history().into_iter().map(|s| s.value).collect();
+        assert_eq!(history, vec![2, 3, 4]);
+        let latest = store.latest().unwrap();
+        assert_eq!(latest.value, 4);
+        assert_eq!(latest.sequence, 4);
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        let store = SampleStore::new(8);
+        let now = chrono::Utc::now();
+        store.push((), None, now, Duration::from_millis(10));
+        store.push((), None, now, Duration::from_millis(30));
+
+        let stats = store.latency();
+        assert_eq!(stats.samples, 2);
+        assert!((stats.last_ms - 30.0).abs() < 1e-9);
+        assert!((stats.max_ms - 30.0).abs() < 1e-9);
+        assert!((stats.mean_ms - 12.0).abs() < 1e-9);
+    }
+}
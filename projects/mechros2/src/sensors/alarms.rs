@@ -0,0 +1,322 @@
+// 🌡️ Environmental Alarm Module
+// File: projects/mechros2/src/sensors/alarms.rs
+
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EnvironmentalQuantity {
+    Temperature,
+    Pressure,
+    LightLevel,
+    ImuTemperature,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AlarmLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlarmCause {
+    BelowRange,
+    AboveRange,
+    RateOfChange,
+}
+
+/// Reacción del sistema ante una alarma activa.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AlarmAction {
+    None,
+    Derate(f64), // Factor de velocidad máxima (0-1)
+    SafeStop,
+}
+
+impl AlarmAction {
+    /// Factor de velocidad resultante: 1 sin restricción, 0 parada.
+    pub fn speed_factor(&self) -> f64 {
+        match self {
+            AlarmAction::None => 1.0,
+            AlarmAction::Derate(factor) => factor.clamp(0.0, 1.0),
+            AlarmAction::SafeStop => 0.0,
+        }
+    }
+
+    fn most_restrictive(self, other: AlarmAction) -> AlarmAction {
+        if other.speed_factor() < self.speed_factor() { other } else { self }
+    }
+}
+
+/// Bandas de aviso/crítico de una magnitud. Los límites ausentes no se vigilan.
+#[derive(Debug, Clone)]
+pub struct AlarmBand {
+    pub warning_low: Option<f64>,
+    pub warning_high: Option<f64>,
+    pub critical_low: Option<f64>,
+    pub critical_high: Option<f64>,
+    pub hysteresis: f64,
+    pub max_rate: Option<f64>, // Variación máxima (unidades/s) antes de avisar
+    pub rate_window_s: f64,
+    pub on_warning: AlarmAction,
+    pub on_critical: AlarmAction,
+}
+
+impl Default for AlarmBand {
+    fn default() -> Self {
+        Self {
+            warning_low: None,
+            warning_high: None,
+            critical_low: None,
+            critical_high: None,
+            hysteresis: 0.0,
+            max_rate: None,
+            rate_window_s: 10.0,
+            on_warning: AlarmAction::None,
+            on_critical: AlarmAction::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvironmentalAlarmConfig {
+    pub bands: HashMap<EnvironmentalQuantity, AlarmBand>,
+}
+
+impl Default for EnvironmentalAlarmConfig {
+    fn default() -> Self {
+        Self {
+            bands: HashMap::from([
+                (EnvironmentalQuantity::Temperature, AlarmBand {
+                    warning_low: Some(0.0),
+                    warning_high: Some(45.0),
+                    critical_low: Some(-10.0),
+                    critical_high: Some(60.0),
+                    hysteresis: 2.0,
+                    max_rate: Some(0.5), // °C/s
+                    on_warning: AlarmAction::Derate(0.5),
+                    on_critical: AlarmAction::SafeStop,
+                    ..AlarmBand::default()
+                }),
+                (EnvironmentalQuantity::Pressure, AlarmBand {
+                    warning_low: Some(900.0),
+                    warning_high: Some(1080.0),
+                    critical_low: Some(800.0),
+                    critical_high: Some(1100.0),
+                    hysteresis: 5.0,
+                    max_rate: Some(1.0), // hPa/s: cambio brusco (puertas, ventilación)
+                    ..AlarmBand::default()
+                }),
+                (EnvironmentalQuantity::LightLevel, AlarmBand {
+                    // Con poca luz la visión pierde fiabilidad
+                    warning_low: Some(20.0),
+                    critical_low: Some(2.0),
+                    hysteresis: 5.0,
+                    on_warning: AlarmAction::Derate(0.7),
+                    on_critical: AlarmAction::Derate(0.3),
+                    ..AlarmBand::default()
+                }),
+                (EnvironmentalQuantity::ImuTemperature, AlarmBand {
+                    warning_high: Some(70.0),
+                    critical_high: Some(85.0),
+                    hysteresis: 3.0,
+                    max_rate: Some(1.0), // °C/s: la deriva térmica invalida la calibración
+                    on_warning: AlarmAction::Derate(0.5),
+                    on_critical: AlarmAction::SafeStop,
+                    ..AlarmBand::default()
+                }),
+            ]),
+        }
+    }
+}
+
+/// Cambio de nivel de alarma de una magnitud.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentalAlarm {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub quantity: EnvironmentalQuantity,
+    pub previous: AlarmLevel,
+    pub level: AlarmLevel,
+    pub cause: Option<AlarmCause>,
+    pub value: f64,
+    pub rate: Option<f64>,
+    pub action: AlarmAction,
+}
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    level: Option<AlarmLevel>,
+    cause: Option<AlarmCause>,
+    history: VecDeque<(chrono::DateTime<chrono::Utc>, f64)>,
+}
+
+pub struct AlarmMonitor {
+    config: EnvironmentalAlarmConfig,
+    channels: HashMap<EnvironmentalQuantity, ChannelState>,
+}
+
+impl AlarmMonitor {
+    pub fn new(config: EnvironmentalAlarmConfig) -> Self {
+        Self {
+            config,
+            channels: HashMap::new(),
+        }
+    }
+
+    /// Evalúa una nueva lectura; devuelve un evento si el nivel cambia.
+    /// Las lecturas con marca de tiempo no posterior a la última se ignoran.
+    pub fn update(
+        &mut self,
+        quantity: EnvironmentalQuantity,
+        value: f64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Option<EnvironmentalAlarm> {
+        let band = self.config.bands.get(&quantity)?;
+        let channel = self.channels.entry(quantity).or_default();
+
+        if channel.history.back().is_some_and(|&(last, _)| timestamp <= last) {
+            return None;
+        }
+        channel.history.push_back((timestamp, value));
+        while channel.history.len() > 2 && channel.history.front().is_some_and(|&(t, _)| {
+            (timestamp - t).num_milliseconds() as f64 / 1000.0 > band.rate_window_s
+        }) {
+            channel.history.pop_front();
+        }
+
+        let rate = rate_of_change(&channel.history, band.rate_window_s / 2.0);
+        let previous = channel.level.unwrap_or(AlarmLevel::Normal);
+        let (level, cause) = band_level(band, value, previous, channel.cause, rate);
+
+        // La primera lectura solo notifica si ya está fuera de rango
+        let changed = match channel.level {
+            None => level != AlarmLevel::Normal,
+            Some(current) => current != level || (level != AlarmLevel::Normal && channel.cause != cause),
+        };
+        channel.level = Some(level);
+        channel.cause = cause;
+
+        changed.then(|| EnvironmentalAlarm {
+            timestamp,
+            quantity,
+            previous,
+            level,
+            cause,
+            value,
+            rate,
+            action: band.action(level),
+        })
+    }
+
+    pub fn level(&self, quantity: EnvironmentalQuantity) -> AlarmLevel {
+        self.channels.get(&quantity).and_then(|c| c.level).unwrap_or(AlarmLevel::Normal)
+    }
+
+    /// Acción más restrictiva entre todas las alarmas activas.
+    pub fn required_action(&self) -> AlarmAction {
+        self.config.bands.iter()
+            .map(|(quantity, band)| band.action(self.level(*quantity)))
+            .fold(AlarmAction::None, AlarmAction::most_restrictive)
+    }
+}
+
+impl AlarmBand {
+    fn action(&self, level: AlarmLevel) -> AlarmAction {
+        match level {
+            AlarmLevel::Normal => AlarmAction::None,
+            AlarmLevel::Warning => self.on_warning,
+            AlarmLevel::Critical => self.on_critical,
+        }
+    }
+}
+
+/// Pendiente entre la lectura más antigua y la más reciente de la ventana.
+/// Con menos de `min_span_s` de historial el ruido dominaría la estimación.
+fn rate_of_change(history: &VecDeque<(chrono::DateTime<chrono::Utc>, f64)>, min_span_s: f64) -> Option<f64> {
+    let (&(t0, v0), &(t1, v1)) = (history.front()?, history.back()?);
+    let dt = (t1 - t0).num_milliseconds() as f64 / 1000.0;
+    (dt > 0.0 && dt >= min_span_s).then(|| (v1 - v0) / dt)
+}
+
+fn band_level(
+    band: &AlarmBand,
+    value: f64,
+    previous: AlarmLevel,
+    previous_cause: Option<AlarmCause>,
+    rate: Option<f64>,
+) -> (AlarmLevel, Option<AlarmCause>) {
+    // Para bajar de nivel hay que volver dentro del umbral más la histéresis
+    let margin = |level: AlarmLevel, cause: AlarmCause| {
+        if previous >= level && previous_cause == Some(cause) { band.hysteresis } else { 0.0 }
+    };
+    let above = |limit: Option<f64>, level| limit.is_some_and(|l| value > l - margin(level, AlarmCause::AboveRange));
+    let below = |limit: Option<f64>, level| limit.is_some_and(|l| value < l + margin(level, AlarmCause::BelowRange));
+
+    if above(band.critical_high, AlarmLevel::Critical) {
+        return (AlarmLevel::Critical, Some(AlarmCause::AboveRange));
+    }
+    if below(band.critical_low, AlarmLevel::Critical) {
+        return (AlarmLevel::Critical, Some(AlarmCause::BelowRange));
+    }
+    if above(band.warning_high, AlarmLevel::Warning) {
+        return (AlarmLevel::Warning, Some(AlarmCause::AboveRange));
+    }
+    if below(band.warning_low, AlarmLevel::Warning) {
+        return (AlarmLevel::Warning, Some(AlarmCause::BelowRange));
+    }
+    if let (Some(max_rate), Some(rate)) = (band.max_rate, rate) {
+        if rate.abs() > max_rate {
+            return (AlarmLevel::Warning, Some(AlarmCause::RateOfChange));
+        }
+    }
+
+    (AlarmLevel::Normal, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(1_700_000_000 + s, 0).unwrap()
+    }
+
+    #[test]
+    fn test_bands_with_hysteresis() {
+        let mut monitor = AlarmMonitor::new(EnvironmentalAlarmConfig::default());
+        let temperature = EnvironmentalQuantity::Temperature;
+
+        assert!(monitor.update(temperature, 25.0, at(0)).is_none());
+
+        // Subida lenta para no disparar la alarma por variación
+        let mut level_at = |value: f64, t: i64| {
+            monitor.update(temperature, value, at(t));
+            monitor.level(temperature)
+        };
+        assert_eq!(level_at(46.0, 100), AlarmLevel::Warning);
+        assert_eq!(level_at(44.0, 110), AlarmLevel::Warning); // Dentro de la histéresis
+        assert_eq!(level_at(61.0, 200), AlarmLevel::Critical);
+        assert_eq!(level_at(59.0, 300), AlarmLevel::Critical);
+        assert_eq!(level_at(57.0, 400), AlarmLevel::Warning);
+        assert_eq!(level_at(42.0, 500), AlarmLevel::Normal);
+
+        assert_eq!(monitor.required_action(), AlarmAction::None);
+    }
+
+    #[test]
+    fn test_rate_of_change_and_action() {
+        let mut monitor = AlarmMonitor::new(EnvironmentalAlarmConfig::default());
+        let imu = EnvironmentalQuantity::ImuTemperature;
+
+        monitor.update(imu, 30.0, at(0));
+        let alarm = monitor.update(imu, 40.0, at(5)).unwrap();
+        assert_eq!(alarm.level, AlarmLevel::Warning);
+        assert_eq!(alarm.cause, Some(AlarmCause::RateOfChange));
+        assert_eq!(monitor.required_action(), AlarmAction::Derate(0.5));
+
+        let alarm = monitor.update(imu, 90.0, at(60)).unwrap();
+        assert_eq!(alarm.level, AlarmLevel::Critical);
+        assert_eq!(monitor.required_action().speed_factor(), 0.0);
+    }
+}
@@ -0,0 +1,243 @@
+// 🔋 Battery Model Module
+// File: projects/mechros2/src/sensors/battery.rs
+
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+pub struct BatteryConfig {
+    pub capacity_ah: f64,
+    pub cell_count: u32,
+    /// Curva tensión por celda (V) -> estado de carga (%), ordenada por tensión.
+    pub voltage_curve: Vec<(f64, f64)>,
+    /// Corriente (A) por debajo de la cual la tensión se considera en reposo.
+    pub rest_current: f64,
+    /// Peso con el que la curva de tensión corrige el conteo de culombios en reposo (1/s).
+    pub voltage_correction_rate: f64,
+    pub low_threshold: f64,      // %
+    pub critical_threshold: f64, // %
+    pub threshold_hysteresis: f64,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            capacity_ah: 10.0,
+            cell_count: 4,
+            // Li-ion típica
+            voltage_curve: vec![
+                (3.00, 0.0),
+                (3.30, 5.0),
+                (3.50, 10.0),
+                (3.60, 20.0),
+                (3.70, 40.0),
+                (3.80, 60.0),
+                (3.90, 75.0),
+                (4.00, 85.0),
+                (4.10, 95.0),
+                (4.20, 100.0),
+            ],
+            rest_current: 0.5,
+            voltage_correction_rate: 0.05,
+            low_threshold: 20.0,
+            critical_threshold: 10.0,
+            threshold_hysteresis: 2.0,
+        }
+    }
+}
+
+/// Lectura cruda del monitor. Convención ROS: corriente negativa al descargar.
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryReading {
+    pub voltage: f64,
+    pub current: f64,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    Idle,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BatteryAlert {
+    Normal,
+    Low,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryEstimate {
+    pub voltage: f64,
+    pub current: f64,
+    pub temperature: Option<f32>,
+    pub state_of_charge: f64, // %
+    pub charge_ah: f64,
+    pub capacity_ah: f64,
+    pub remaining_runtime_s: Option<f64>,
+    pub charge_state: ChargeState,
+    pub alert: BatteryAlert,
+}
+
+/// Estimación del estado de carga combinando conteo de culombios con la curva
+/// de tensión, que corrige la deriva cuando la batería está en reposo.
+pub struct BatteryModel {
+    config: BatteryConfig,
+    state_of_charge: Option<f64>,
+    alert: BatteryAlert,
+    last_update: Option<Instant>,
+}
+
+impl BatteryModel {
+    pub fn new(config: BatteryConfig) -> Self {
+        Self {
+            config,
+            state_of_charge: None,
+            alert: BatteryAlert::Normal,
+            last_update: None,
+        }
+    }
+
+    pub fn config(&self) -> &BatteryConfig {
+        &self.config
+    }
+
+    /// Estado de carga según la curva de tensión, interpolando linealmente.
+    pub fn soc_from_voltage(&self, pack_voltage: f64) -> f64 {
+        let cell_voltage = pack_voltage / self.config.cell_count.max(1) as f64;
+        let curve = &self.config.voltage_curve;
+
+        match curve.iter().position(|&(v, _)| v >= cell_voltage) {
+            None => curve.last().map_or(100.0, |&(_, soc)| soc),
+            Some(0) => curve[0].1,
+            Some(i) => {
+                let (v0, s0) = curve[i - 1];
+                let (v1, s1) = curve[i];
+                s0 + (s1 - s0) * (cell_voltage - v0) / (v1 - v0)
+            }
+        }
+    }
+
+    /// Actualiza usando el reloj monotónico para calcular `dt`.
+    pub fn update_at(&mut self, reading: &BatteryReading, now: Instant) -> BatteryEstimate {
+        let dt = self.last_update.map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_update = Some(now);
+        self.update(reading, dt)
+    }
+
+    pub fn update(&mut self, reading: &BatteryReading, dt: f64) -> BatteryEstimate {
+        let voltage_soc = self.soc_from_voltage(reading.voltage);
+
+        let soc = match self.state_of_charge {
+            None => voltage_soc,
+            Some(previous) => {
+                // Conteo de culombios
+                let mut soc = previous + reading.current * dt / 3600.0 / self.config.capacity_ah * 100.0;
+
+                // En reposo la tensión es fiable: corregir la deriva poco a poco
+                if reading.current.abs() < self.config.rest_current {
+                    let gain = (self.config.voltage_correction_rate * dt).min(1.0);
+                    soc += (voltage_soc - soc) * gain;
+                }
+                soc
+            }
+        }
+        .clamp(0.0, 100.0);
+
+        self.state_of_charge = Some(soc);
+        self.alert = self.next_alert(soc);
+
+        let charge_ah = soc / 100.0 * self.config.capacity_ah;
+        let charge_state = if reading.current > self.config.rest_current * 0.1 {
+            if soc >= 99.5 { ChargeState::Full } else { ChargeState::Charging }
+        } else if reading.current < -self.config.rest_current * 0.1 {
+            ChargeState::Discharging
+        } else if soc >= 99.5 {
+            ChargeState::Full
+        } else {
+            ChargeState::Idle
+        };
+
+        let remaining_runtime_s = match charge_state {
+            ChargeState::Discharging => Some(charge_ah / reading.current.abs() * 3600.0),
+            _ => None,
+        };
+
+        BatteryEstimate {
+            voltage: reading.voltage,
+            current: reading.current,
+            temperature: reading.temperature,
+            state_of_charge: soc,
+            charge_ah,
+            capacity_ah: self.config.capacity_ah,
+            remaining_runtime_s,
+            charge_state,
+            alert: self.alert,
+        }
+    }
+
+    fn next_alert(&self, soc: f64) -> BatteryAlert {
+        let hysteresis = self.config.threshold_hysteresis;
+        let critical = self.config.critical_threshold;
+        let low = self.config.low_threshold;
+
+        // Para salir de un nivel de alerta hay que superar el umbral más la histéresis
+        match self.alert {
+            BatteryAlert::Critical if soc < critical + hysteresis => BatteryAlert::Critical,
+            BatteryAlert::Low | BatteryAlert::Critical if soc < low + hysteresis => {
+                if soc < critical { BatteryAlert::Critical } else { BatteryAlert::Low }
+            }
+            _ if soc < critical => BatteryAlert::Critical,
+            _ if soc < low => BatteryAlert::Low,
+            _ => BatteryAlert::Normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_voltage_curve_lookup() {
+        let model = BatteryModel::new(BatteryConfig::default());
+        assert_relative_eq!(model.soc_from_voltage(4.0 * 3.75), 50.0, epsilon = 1e-9);
+        assert_relative_eq!(model.soc_from_voltage(4.0 * 4.3), 100.0);
+        assert_relative_eq!(model.soc_from_voltage(4.0 * 2.8), 0.0);
+    }
+
+    #[test]
+    fn test_coulomb_counting_and_runtime() {
+        let mut model = BatteryModel::new(BatteryConfig::default());
+        let reading = BatteryReading { voltage: 4.0 * 3.75, current: -5.0, temperature: None };
+
+        let first = model.update(&reading, 0.0);
+        assert_relative_eq!(first.state_of_charge, 50.0, epsilon = 1e-9);
+
+        // 5 A durante 36 s en una batería de 10 Ah consume 0.5 %
+        let estimate = model.update(&reading, 36.0);
+        assert_relative_eq!(estimate.state_of_charge, 49.5, epsilon = 1e-9);
+        assert_eq!(estimate.charge_state, ChargeState::Discharging);
+        assert_relative_eq!(estimate.remaining_runtime_s.unwrap(), 4.95 / 5.0 * 3600.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_alert_hysteresis() {
+        let mut model = BatteryModel::new(BatteryConfig::default());
+        let mut alert_at = |soc: f64| {
+            model.alert = model.next_alert(soc);
+            model.alert
+        };
+
+        assert_eq!(alert_at(19.0), BatteryAlert::Low);
+        assert_eq!(alert_at(21.0), BatteryAlert::Low); // Dentro de la histéresis
+        assert_eq!(alert_at(23.0), BatteryAlert::Normal);
+        assert_eq!(alert_at(9.0), BatteryAlert::Critical);
+        assert_eq!(alert_at(11.0), BatteryAlert::Critical);
+        assert_eq!(alert_at(12.5), BatteryAlert::Low);
+    }
+}
@@ -0,0 +1,416 @@
+// 🎯 IMU Calibration Module
+// File: projects/mechros2/src/sensors/calibration.rs
+
+use std::path::{Path, PathBuf};
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use super::{ImuData, RawImuSample};
+
+/// Gravedad estándar usada como referencia en la calibración de seis posiciones.
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Ruta por defecto del archivo de calibración persistido.
+pub const DEFAULT_CALIBRATION_PATH: &str = "config/imu_calibration.json";
+
+/// Parámetros de calibración del IMU, persistidos en disco y aplicados a cada muestra.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImuCalibration {
+    pub gyro_bias: Vector3<f64>,
+    pub accel_offset: Vector3<f64>,
+    pub accel_scale: Vector3<f64>,
+    pub mag_hard_iron: Vector3<f64>,
+    pub mag_soft_iron: Matrix3<f64>,
+    pub calibrated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Default for ImuCalibration {
+    fn default() -> Self {
+        Self {
+            gyro_bias: Vector3::zeros(),
+            accel_offset: Vector3::zeros(),
+            accel_scale: Vector3::new(1.0, 1.0, 1.0),
+            mag_hard_iron: Vector3::zeros(),
+            mag_soft_iron: Matrix3::identity(),
+            calibrated_at: chrono::Utc::now(),
+        }
+    }
+}
+
+impl ImuCalibration {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let calibration = serde_json::from_str(&contents)?;
+        Ok(calibration)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    pub fn correct_gyro(&self, raw: &Vector3<f64>) -> Vector3<f64> {
+        raw - self.gyro_bias
+    }
+
+    pub fn correct_accel(&self, raw: &Vector3<f64>) -> Vector3<f64> {
+        (raw - self.accel_offset).component_div(&self.accel_scale)
+    }
+
+    pub fn correct_magnetometer(&self, raw: &Vector3<f64>) -> Vector3<f64> {
+        self.mag_soft_iron * (raw - self.mag_hard_iron)
+    }
+
+    /// Aplica la calibración a una muestra ya convertida a `ImuData`.
+    pub fn apply(&self, data: &mut ImuData) {
+        data.linear_acceleration = self.correct_accel(&data.linear_acceleration);
+        data.angular_velocity = self.correct_gyro(&data.angular_velocity);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImuCalibrationConfig {
+    pub file_path: PathBuf,
+    pub gyro_samples: usize,
+    pub samples_per_position: usize,
+    pub sample_interval_ms: u64,
+    pub gyro_motion_threshold: f64,  // rad/s de desviación máxima
+    pub accel_motion_threshold: f64, // m/s² de desviación estándar de |a|
+    pub mag_min_samples: usize,
+}
+
+impl Default for ImuCalibrationConfig {
+    fn default() -> Self {
+        Self {
+            file_path: PathBuf::from(DEFAULT_CALIBRATION_PATH),
+            gyro_samples: 200,
+            samples_per_position: 100,
+            sample_interval_ms: 5,
+            gyro_motion_threshold: 0.05,
+            accel_motion_threshold: 0.2,
+            mag_min_samples: 50,
+        }
+    }
+}
+
+/// Verifica que el robot permaneció quieto mientras se tomaban las muestras.
+pub fn check_stationary(samples: &[RawImuSample], config: &ImuCalibrationConfig) -> Result<(), Box<dyn std::error::Error>> {
+    if samples.is_empty() {
+        return Err("Sin muestras de IMU para calibrar".into());
+    }
+
+    let n = samples.len() as f64;
+    let gyro_mean = samples.iter().map(|s| s.gyro).sum::<Vector3<f64>>() / n;
+    let max_gyro_deviation = samples.iter()
+        .map(|s| (s.gyro - gyro_mean).norm())
+        .fold(0.0, f64::max);
+
+    if max_gyro_deviation > config.gyro_motion_threshold {
+        return Err(format!("Movimiento detectado durante la calibración (giro {:.3} rad/s)",
+                           max_gyro_deviation).into());
+    }
+
+    let accel_norm_mean = samples.iter().map(|s| s.accel.norm()).sum::<f64>() / n;
+    let accel_norm_std = (samples.iter()
+        .map(|s| (s.accel.norm() - accel_norm_mean).powi(2))
+        .sum::<f64>() / n).sqrt();
+
+    if accel_norm_std > config.accel_motion_threshold {
+        return Err(format!("Movimiento detectado durante la calibración (aceleración σ={:.3} m/s²)",
+                           accel_norm_std).into());
+    }
+
+    Ok(())
+}
+
+/// Estima el bias del giroscopio con el robot inmóvil; aborta si detecta movimiento.
+pub fn estimate_gyro_bias(samples: &[RawImuSample], config: &ImuCalibrationConfig) -> Result<Vector3<f64>, Box<dyn std::error::Error>> {
+    check_stationary(samples, config)?;
+    Ok(samples.iter().map(|s| s.gyro).sum::<Vector3<f64>>() / samples.len() as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccelPosition {
+    XUp,
+    XDown,
+    YUp,
+    YDown,
+    ZUp,
+    ZDown,
+}
+
+impl AccelPosition {
+    pub const ALL: [AccelPosition; 6] = [
+        AccelPosition::XUp,
+        AccelPosition::XDown,
+        AccelPosition::YUp,
+        AccelPosition::YDown,
+        AccelPosition::ZUp,
+        AccelPosition::ZDown,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            AccelPosition::XUp => 0,
+            AccelPosition::XDown => 1,
+            AccelPosition::YUp => 2,
+            AccelPosition::YDown => 3,
+            AccelPosition::ZUp => 4,
+            AccelPosition::ZDown => 5,
+        }
+    }
+}
+
+/// Paso de calibración explícita pedido por el operador. Cada paso completado
+/// se guarda en el archivo de calibración.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum ImuCalibrationStep {
+    Gyro,                                     // Robot inmóvil
+    AccelPosition { position: AccelPosition }, // Una de las seis posiciones
+    Magnetometer { duration_s: f64 },          // Rotar el robot en todas las direcciones
+}
+
+/// Calibración del acelerómetro en seis posiciones (cada eje hacia arriba y hacia abajo).
+#[derive(Debug, Clone, Default)]
+pub struct SixPositionCalibrator {
+    readings: [Option<Vector3<f64>>; 6],
+}
+
+impl SixPositionCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_position(&mut self, position: AccelPosition, samples: &[RawImuSample], config: &ImuCalibrationConfig) -> Result<(), Box<dyn std::error::Error>> {
+        check_stationary(samples, config)?;
+        let mean = samples.iter().map(|s| s.accel).sum::<Vector3<f64>>() / samples.len() as f64;
+        self.readings[position.index()] = Some(mean);
+        Ok(())
+    }
+
+    pub fn missing_positions(&self) -> Vec<AccelPosition> {
+        AccelPosition::ALL.iter()
+            .copied()
+            .filter(|p| self.readings[p.index()].is_none())
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.readings.iter().all(Option::is_some)
+    }
+
+    /// Devuelve `(offset, escala)` por eje.
+    pub fn solve(&self) -> Result<(Vector3<f64>, Vector3<f64>), Box<dyn std::error::Error>> {
+        if !self.is_complete() {
+            return Err(format!("Faltan posiciones de calibración: {:?}", self.missing_positions()).into());
+        }
+
+        let mut offset = Vector3::zeros();
+        let mut scale = Vector3::zeros();
+
+        for axis in 0..3 {
+            let up = self.readings[axis * 2].unwrap()[axis];
+            let down = self.readings[axis * 2 + 1].unwrap()[axis];
+            offset[axis] = (up + down) / 2.0;
+            scale[axis] = (up - down) / (2.0 * STANDARD_GRAVITY);
+
+            if scale[axis] <= 0.0 {
+                return Err(format!("Escala inválida en el eje {}: posiciones invertidas", axis).into());
+            }
+        }
+
+        Ok((offset, scale))
+    }
+}
+
+/// Ajuste de elipsoide para el magnetómetro. Devuelve `(hard_iron, soft_iron)` tal que
+/// `soft_iron * (raw - hard_iron)` queda sobre una esfera del radio medio del campo.
+pub fn fit_magnetometer_ellipsoid(samples: &[Vector3<f64>]) -> Result<(Vector3<f64>, Matrix3<f64>), Box<dyn std::error::Error>> {
+    if samples.len() < 9 {
+        return Err(format!("Se necesitan al menos 9 muestras de magnetómetro, recibidas {}", samples.len()).into());
+    }
+
+    // a·x² + b·y² + c·z² + 2d·xy + 2e·xz + 2f·yz + 2g·x + 2h·y + 2i·z = 1
+    let design = DMatrix::from_fn(samples.len(), 9, |row, col| {
+        let s = &samples[row];
+        match col {
+            0 => s.x * s.x,
+            1 => s.y * s.y,
+            2 => s.z * s.z,
+            3 => 2.0 * s.x * s.y,
+            4 => 2.0 * s.x * s.z,
+            5 => 2.0 * s.y * s.z,
+            6 => 2.0 * s.x,
+            7 => 2.0 * s.y,
+            _ => 2.0 * s.z,
+        }
+    });
+    let ones = DVector::from_element(samples.len(), 1.0);
+
+    let params = design.svd(true, true).solve(&ones, 1e-12)?;
+
+    let quadric = Matrix3::new(
+        params[0], params[3], params[4],
+        params[3], params[1], params[5],
+        params[4], params[5], params[2],
+    );
+    let linear = Vector3::new(params[6], params[7], params[8]);
+
+    let quadric_inv = quadric.try_inverse().ok_or("Ajuste de elipsoide degenerado: rote el robot en todas las direcciones")?;
+    let center = -(quadric_inv * linear);
+
+    let k = 1.0 + center.dot(&(quadric * center));
+    if k <= 0.0 {
+        return Err("Ajuste de elipsoide inválido".into());
+    }
+    let normalized = quadric / k;
+
+    let eigen = normalized.symmetric_eigen();
+    if eigen.eigenvalues.iter().any(|&l| l <= 0.0) {
+        return Err("Las muestras del magnetómetro no forman un elipsoide".into());
+    }
+
+    // Radio medio del elipsoide: media geométrica de los semiejes
+    let radius = eigen.eigenvalues.iter().map(|l| l.powf(-1.0 / 6.0)).product::<f64>();
+    let sqrt_diag = Matrix3::from_diagonal(&eigen.eigenvalues.map(f64::sqrt));
+    let soft_iron = eigen.eigenvectors * sqrt_diag * eigen.eigenvectors.transpose() * radius;
+
+    Ok((center, soft_iron))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn sample(accel: Vector3<f64>, gyro: Vector3<f64>) -> RawImuSample {
+        RawImuSample {
+            accel,
+            gyro,
+            mag: None,
+            temperature: 25.0,
+        }
+    }
+
+    #[test]
+    fn test_calibration_step_from_remote_command() {
+        let parse = |json: &str| serde_json::from_str::<ImuCalibrationStep>(json).unwrap();
+        assert_eq!(parse(r#"{"command": "calibrate_imu", "step": "gyro"}"#), ImuCalibrationStep::Gyro);
+        assert_eq!(
+            parse(r#"{"command": "calibrate_imu", "step": "accel_position", "position": "ZDown"}"#),
+            ImuCalibrationStep::AccelPosition { position: AccelPosition::ZDown }
+        );
+        assert_eq!(
+            parse(r#"{"command": "calibrate_imu", "step": "magnetometer", "duration_s": 30.0}"#),
+            ImuCalibrationStep::Magnetometer { duration_s: 30.0 }
+        );
+        assert!(serde_json::from_str::<ImuCalibrationStep>(r#"{"step": "accel_position"}"#).is_err());
+    }
+
+    #[test]
+    fn test_gyro_bias_stationary() {
+        let config = ImuCalibrationConfig::default();
+        let samples: Vec<_> = (0..100)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 0.001 } else { -0.001 };
+                sample(Vector3::new(0.0, 0.0, 9.81), Vector3::new(0.01 + noise, 0.02, -0.005))
+            })
+            .collect();
+
+        let bias = estimate_gyro_bias(&samples, &config).unwrap();
+        assert_relative_eq!(bias, Vector3::new(0.01, 0.02, -0.005), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_gyro_bias_aborts_on_motion() {
+        let config = ImuCalibrationConfig::default();
+        let mut samples: Vec<_> = (0..100)
+            .map(|_| sample(Vector3::new(0.0, 0.0, 9.81), Vector3::zeros()))
+            .collect();
+        samples[50].gyro = Vector3::new(0.0, 0.0, 0.8);
+
+        assert!(estimate_gyro_bias(&samples, &config).is_err());
+    }
+
+    #[test]
+    fn test_six_position_calibration() {
+        let config = ImuCalibrationConfig::default();
+        let true_offset = Vector3::new(0.1, -0.2, 0.3);
+        let true_scale = Vector3::new(1.02, 0.98, 1.01);
+        let mut calibrator = SixPositionCalibrator::new();
+
+        for position in AccelPosition::ALL {
+            let g = STANDARD_GRAVITY;
+            let ideal = match position {
+                AccelPosition::XUp => Vector3::new(g, 0.0, 0.0),
+                AccelPosition::XDown => Vector3::new(-g, 0.0, 0.0),
+                AccelPosition::YUp => Vector3::new(0.0, g, 0.0),
+                AccelPosition::YDown => Vector3::new(0.0, -g, 0.0),
+                AccelPosition::ZUp => Vector3::new(0.0, 0.0, g),
+                AccelPosition::ZDown => Vector3::new(0.0, 0.0, -g),
+            };
+            let raw = ideal.component_mul(&true_scale) + true_offset;
+            let samples = vec![sample(raw, Vector3::zeros()); 10];
+            calibrator.add_position(position, &samples, &config).unwrap();
+        }
+
+        let (offset, scale) = calibrator.solve().unwrap();
+        assert_relative_eq!(offset, true_offset, epsilon = 1e-9);
+        assert_relative_eq!(scale, true_scale, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_magnetometer_ellipsoid_fit() {
+        let hard_iron = Vector3::new(0.3, -0.1, 0.2);
+        let distortion = Matrix3::new(
+            1.2, 0.1, 0.0,
+            0.1, 0.9, 0.05,
+            0.0, 0.05, 1.1,
+        );
+        let field = 0.5;
+
+        let mut samples = Vec::new();
+        for i in 0..12 {
+            for j in 0..24 {
+                let theta = std::f64::consts::PI * (i as f64 + 0.5) / 12.0;
+                let phi = 2.0 * std::f64::consts::PI * j as f64 / 24.0;
+                let dir = Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+                samples.push(distortion * dir * field + hard_iron);
+            }
+        }
+
+        let (center, soft_iron) = fit_magnetometer_ellipsoid(&samples).unwrap();
+        assert_relative_eq!(center, hard_iron, epsilon = 1e-6);
+
+        let calibration = ImuCalibration {
+            mag_hard_iron: center,
+            mag_soft_iron: soft_iron,
+            ..ImuCalibration::default()
+        };
+        let radii: Vec<f64> = samples.iter().map(|s| calibration.correct_magnetometer(s).norm()).collect();
+        let mean = radii.iter().sum::<f64>() / radii.len() as f64;
+        for r in radii {
+            assert_relative_eq!(r, mean, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_calibration_roundtrip() {
+        let path = std::env::temp_dir().join(format!("mechros2_imu_cal_{}.json", std::process::id()));
+        let calibration = ImuCalibration {
+            gyro_bias: Vector3::new(0.01, 0.02, 0.0),
+            ..ImuCalibration::default()
+        };
+        calibration.save(&path).unwrap();
+        let loaded = ImuCalibration::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_relative_eq!(loaded.gyro_bias, calibration.gyro_bias);
+    }
+}
@@ -0,0 +1,192 @@
+// 🧭 Compass Heading Module
+// File: projects/mechros2/src/sensors/compass.rs
+//
+// Convención: el rumbo se expresa como yaw del marco mundo (x = norte
+// geográfico, z arriba), en radianes y positivo en sentido antihorario, igual
+// que `RollPitchYaw::yaw`. El rumbo GPS (grados horarios desde el norte) se
+// convierte a esta convención.
+
+use std::time::Instant;
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use super::orientation::wrap_angle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeadingSource {
+    /// Rumbo sobre el terreno del GPS; solo válido en movimiento.
+    GpsCourse,
+    /// Magnetómetro compensado en inclinación.
+    Compass,
+    /// Giroscopio corregido por brújula y, en movimiento, por el rumbo GPS.
+    Fused,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompassConfig {
+    pub source: HeadingSource,
+    /// Declinación magnética local en grados (positiva hacia el este).
+    pub declination_deg: f64,
+    /// Velocidad mínima (m/s) para considerar válido el rumbo GPS.
+    pub min_gps_speed: f64,
+    /// Ganancias de corrección (1/s) del modo fusionado.
+    pub compass_gain: f64,
+    pub gps_gain: f64,
+    /// Intensidad esperada del campo (misma unidad que el magnetómetro) y desviación
+    /// relativa admitida; fuera de ella se asume perturbación y se ignora la brújula.
+    pub expected_field_strength: Option<f64>,
+    pub max_field_deviation: f64,
+}
+
+impl Default for CompassConfig {
+    fn default() -> Self {
+        Self {
+            source: HeadingSource::Fused,
+            declination_deg: 0.0,
+            min_gps_speed: 0.5,
+            compass_gain: 0.5,
+            gps_gain: 1.0,
+            expected_field_strength: None,
+            max_field_deviation: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadingEstimate {
+    pub heading: f64,                 // Yaw respecto al norte geográfico (rad)
+    pub source: HeadingSource,
+    pub compass_heading: Option<f64>, // Ya corregido por declinación
+    pub gps_course: Option<f64>,
+}
+
+/// Yaw magnético a partir del campo medido en el marco del robot, proyectado
+/// sobre el plano horizontal con el roll y pitch actuales.
+pub fn tilt_compensated_yaw(mag: &Vector3<f64>, roll: f64, pitch: f64) -> f64 {
+    let level = UnitQuaternion::from_euler_angles(roll, pitch, 0.0) * mag;
+    (-level.y).atan2(level.x)
+}
+
+/// Convierte un rumbo GPS (grados horarios desde el norte) a yaw.
+pub fn course_to_yaw(course_deg: f64) -> f64 {
+    wrap_angle(-course_deg.to_radians())
+}
+
+pub struct HeadingEstimator {
+    config: CompassConfig,
+    heading: Option<f64>,
+    last_update: Option<Instant>,
+}
+
+impl HeadingEstimator {
+    pub fn new(config: CompassConfig) -> Self {
+        Self {
+            config,
+            heading: None,
+            last_update: None,
+        }
+    }
+
+    /// Rumbo de brújula corregido por declinación; `None` si el campo está perturbado.
+    pub fn compass_heading(&self, mag: &Vector3<f64>, roll: f64, pitch: f64) -> Option<f64> {
+        let strength = mag.norm();
+        if strength <= 0.0 {
+            return None;
+        }
+        if let Some(expected) = self.config.expected_field_strength {
+            if ((strength - expected) / expected).abs() > self.config.max_field_deviation {
+                return None;
+            }
+        }
+
+        let magnetic = tilt_compensated_yaw(mag, roll, pitch);
+        Some(wrap_angle(magnetic - self.config.declination_deg.to_radians()))
+    }
+
+    /// Rumbo GPS como yaw, solo si la velocidad permite confiar en él.
+    pub fn gps_course(&self, course_deg: f64, speed: f64) -> Option<f64> {
+        (speed >= self.config.min_gps_speed).then(|| course_to_yaw(course_deg))
+    }
+
+    /// Actualiza usando el reloj monotónico para calcular `dt`.
+    pub fn update_at(&mut self, compass: Option<f64>, gps_course: Option<f64>, yaw_rate: Option<f64>, now: Instant) -> Option<HeadingEstimate> {
+        let dt = self.last_update.map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_update = Some(now);
+        self.update(compass, gps_course, yaw_rate, dt)
+    }
+
+    /// Sin medida de la fuente seleccionada el rumbo se propaga con el giroscopio.
+    pub fn update(&mut self, compass: Option<f64>, gps_course: Option<f64>, yaw_rate: Option<f64>, dt: f64) -> Option<HeadingEstimate> {
+        let predicted = self.heading.map(|h| wrap_angle(h + yaw_rate.unwrap_or(0.0) * dt));
+
+        let heading = match self.config.source {
+            HeadingSource::Compass => compass.or(predicted),
+            HeadingSource::GpsCourse => gps_course.or(predicted),
+            HeadingSource::Fused => {
+                let mut heading = predicted.or(compass).or(gps_course);
+                if let Some(h) = heading.as_mut() {
+                    if let Some(c) = compass {
+                        *h = wrap_angle(*h + wrap_angle(c - *h) * (self.config.compass_gain * dt).min(1.0));
+                    }
+                    if let Some(g) = gps_course {
+                        *h = wrap_angle(*h + wrap_angle(g - *h) * (self.config.gps_gain * dt).min(1.0));
+                    }
+                }
+                heading
+            }
+        }?;
+
+        self.heading = Some(heading);
+        Some(HeadingEstimate {
+            heading,
+            source: self.config.source,
+            compass_heading: compass,
+            gps_course,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_tilt_compensation_and_declination() {
+        let yaw = 0.6_f64;
+        let field_world = Vector3::new(25.0, 0.0, -37.0);
+
+        // Robot girado e inclinado: el rumbo no debe depender de la inclinación
+        let body = UnitQuaternion::from_euler_angles(0.2, -0.15, yaw);
+        let mag = body.inverse() * field_world;
+        let (roll, pitch, _) = body.euler_angles();
+        assert_relative_eq!(tilt_compensated_yaw(&mag, roll, pitch), yaw, epsilon = 1e-9);
+
+        let estimator = HeadingEstimator::new(CompassConfig { declination_deg: 10.0, ..CompassConfig::default() });
+        let heading = estimator.compass_heading(&mag, roll, pitch).unwrap();
+        assert_relative_eq!(heading, yaw - 10f64.to_radians(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_gps_course_requires_motion() {
+        let estimator = HeadingEstimator::new(CompassConfig::default());
+        assert!(estimator.gps_course(90.0, 0.1).is_none());
+        // Rumbo 90° (este) equivale a yaw -π/2 con x al norte
+        assert_relative_eq!(estimator.gps_course(90.0, 2.0).unwrap(), -std::f64::consts::FRAC_PI_2, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_fused_heading_follows_gyro_and_converges() {
+        let mut estimator = HeadingEstimator::new(CompassConfig::default());
+        estimator.update(Some(0.0), None, None, 0.0);
+
+        // Sin brújula, el giroscopio propaga el rumbo
+        let estimate = estimator.update(None, None, Some(0.5), 1.0).unwrap();
+        assert_relative_eq!(estimate.heading, 0.5, epsilon = 1e-9);
+
+        // La brújula corrige la deriva poco a poco
+        for _ in 0..300 {
+            estimator.update(Some(0.3), None, Some(0.0), 0.05);
+        }
+        assert_relative_eq!(estimator.update(Some(0.3), None, None, 0.05).unwrap().heading, 0.3, epsilon = 1e-3);
+    }
+}
@@ -0,0 +1,236 @@
+// 🩺 Sensor Health Module
+// File: projects/mechros2/src/sensors/health.rs
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SensorKind {
+    Imu,
+    Gps,
+    Lidar,
+    Environmental,
+    Proximity,
+    Battery,
+    WheelEncoders,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SensorHealthState {
+    Offline,  // Sin ninguna muestra todavía
+    Ok,
+    Degraded, // Frecuencia medida por debajo de la esperada o fallos aislados
+    Stale,    // La última muestra es demasiado antigua
+    Failed,   // Demasiados fallos consecutivos
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    pub expected_rate_hz: f64,
+    pub stale_periods: f64,           // Periodos sin muestra antes de marcar como obsoleto
+    pub min_rate_ratio: f64,          // Fracción de la frecuencia esperada aceptable
+    pub max_consecutive_failures: u32,
+    pub rate_smoothing: f64,          // Factor EWMA para el intervalo entre muestras
+}
+
+impl HealthConfig {
+    pub fn with_rate(expected_rate_hz: f64) -> Self {
+        Self {
+            expected_rate_hz,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            expected_rate_hz: 20.0,
+            stale_periods: 5.0,
+            min_rate_ratio: 0.7,
+            max_consecutive_failures: 5,
+            rate_smoothing: 0.1,
+        }
+    }
+}
+
+/// Estado de salud de un sensor tal como se expone en `SensorStatus`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorHealth {
+    pub sensor: SensorKind,
+    pub state: SensorHealthState,
+    pub last_sample: Option<chrono::DateTime<chrono::Utc>>,
+    pub expected_rate_hz: f64,
+    pub measured_rate_hz: f64,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+}
+
+impl SensorHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.state, SensorHealthState::Ok | SensorHealthState::Degraded)
+    }
+}
+
+/// Cambio de estado de salud emitido por el hub de sensores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorEvent {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub sensor: SensorKind,
+    pub previous: SensorHealthState,
+    pub current: SensorHealthState,
+    pub detail: Option<String>,
+}
+
+pub struct HealthTracker {
+    config: HealthConfig,
+    health: SensorHealth,
+    mean_interval: Option<f64>,
+}
+
+impl HealthTracker {
+    pub fn new(sensor: SensorKind, config: HealthConfig) -> Self {
+        let health = SensorHealth {
+            sensor,
+            state: SensorHealthState::Offline,
+            last_sample: None,
+            expected_rate_hz: config.expected_rate_hz,
+            measured_rate_hz: 0.0,
+            consecutive_failures: 0,
+            total_failures: 0,
+            last_error: None,
+        };
+
+        Self {
+            config,
+            health,
+            mean_interval: None,
+        }
+    }
+
+    pub fn health(&self) -> &SensorHealth {
+        &self.health
+    }
+
+    pub fn record_success(&mut self, now: chrono::DateTime<chrono::Utc>) -> Option<SensorEvent> {
+        if let Some(last) = self.health.last_sample {
+            let interval = (now - last).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+            if interval > 0.0 {
+                let alpha = self.config.rate_smoothing;
+                let mean = match self.mean_interval {
+                    Some(mean) => mean + alpha * (interval - mean),
+                    None => interval,
+                };
+                self.mean_interval = Some(mean);
+                self.health.measured_rate_hz = 1.0 / mean;
+            }
+        }
+
+        self.health.last_sample = Some(now);
+        self.health.consecutive_failures = 0;
+        self.evaluate(now)
+    }
+
+    pub fn record_failure(&mut self, error: String, now: chrono::DateTime<chrono::Utc>) -> Option<SensorEvent> {
+        self.health.consecutive_failures += 1;
+        self.health.total_failures += 1;
+        self.health.last_error = Some(error);
+        self.evaluate(now)
+    }
+
+    /// Recalcula el estado; devuelve un evento si ha cambiado.
+    pub fn evaluate(&mut self, now: chrono::DateTime<chrono::Utc>) -> Option<SensorEvent> {
+        let current = self.compute_state(now);
+        let previous = self.health.state;
+
+        if current == previous {
+            return None;
+        }
+
+        self.health.state = current;
+        Some(SensorEvent {
+            timestamp: now,
+            sensor: self.health.sensor,
+            previous,
+            current,
+            detail: self.health.last_error.clone(),
+        })
+    }
+
+    pub fn is_stale(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match self.health.last_sample {
+            Some(last) => {
+                let age = (now - last).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+                age > self.config.stale_periods / self.config.expected_rate_hz
+            }
+            None => true,
+        }
+    }
+
+    fn compute_state(&self, now: chrono::DateTime<chrono::Utc>) -> SensorHealthState {
+        if self.health.consecutive_failures >= self.config.max_consecutive_failures {
+            return SensorHealthState::Failed;
+        }
+        if self.health.last_sample.is_none() {
+            return SensorHealthState::Offline;
+        }
+        if self.is_stale(now) {
+            return SensorHealthState::Stale;
+        }
+
+        let rate_too_low = self.mean_interval.is_some()
+            && self.health.measured_rate_hz < self.config.expected_rate_hz * self.config.min_rate_ratio;
+        if rate_too_low || self.health.consecutive_failures > 0 {
+            return SensorHealthState::Degraded;
+        }
+
+        SensorHealthState::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn test_rate_and_state_transitions() {
+        let mut tracker = HealthTracker::new(SensorKind::Imu, HealthConfig::with_rate(20.0));
+
+        let event = tracker.record_success(at(0)).unwrap();
+        assert_eq!(event.previous, SensorHealthState::Offline);
+        assert_eq!(event.current, SensorHealthState::Ok);
+
+        for i in 1..20 {
+            tracker.record_success(at(i * 50));
+        }
+        assert!((tracker.health().measured_rate_hz - 20.0).abs() < 1e-6);
+
+        // Sin muestras durante un segundo: obsoleto
+        let event = tracker.evaluate(at(19 * 50 + 1000)).unwrap();
+        assert_eq!(event.current, SensorHealthState::Stale);
+    }
+
+    #[test]
+    fn test_consecutive_failures() {
+        let mut tracker = HealthTracker::new(SensorKind::Lidar, HealthConfig::with_rate(10.0));
+        tracker.record_success(at(0));
+
+        let event = tracker.record_failure("timeout".to_string(), at(100)).unwrap();
+        assert_eq!(event.current, SensorHealthState::Degraded);
+
+        let mut last = None;
+        for i in 2..=5 {
+            if let Some(e) = tracker.record_failure("timeout".to_string(), at(i * 100)) {
+                last = Some(e);
+            }
+        }
+        let last = last.unwrap();
+        assert_eq!(last.current, SensorHealthState::Failed);
+        assert_eq!(last.detail.as_deref(), Some("timeout"));
+        assert_eq!(tracker.health().total_failures, 5);
+    }
+}
@@ -0,0 +1,402 @@
+// 📡 LiDAR Scan Filter Module
+// File: projects/mechros2/src/sensors/lidar_filter.rs
+
+use nalgebra::{Isometry3, Point2, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use super::LidarData;
+use super::orientation::wrap_angle;
+
+/// Sector angular (en el marco del LiDAR) que se descarta, p. ej. el propio chasis.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AngularMask {
+    pub angle_min: f32,
+    pub angle_max: f32,
+}
+
+impl AngularMask {
+    fn contains(&self, angle: f32) -> bool {
+        let wrap = |a: f32| wrap_angle(a as f64) as f32;
+        let angle = wrap(angle);
+        let (min, max) = (wrap(self.angle_min), wrap(self.angle_max));
+        if min <= max {
+            angle >= min && angle <= max
+        } else {
+            // El sector cruza ±π
+            angle >= min || angle <= max
+        }
+    }
+}
+
+/// Etapas de la cadena de filtrado; se aplican en el orden configurado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScanFilter {
+    /// Invalida lecturas fuera de `[range_min, range_max]` del sensor, con límites adicionales opcionales.
+    RangeClip { min: Option<f32>, max: Option<f32> },
+    /// Invalida NaN, infinitos y valores no positivos.
+    InvalidRemoval,
+    /// Mediana sobre una ventana de haces vecinos.
+    Median { window: usize },
+    /// Elimina puntos "sombra" en los bordes de objetos (ángulo de incidencia rasante).
+    Shadow { min_angle: f32, neighbors: usize },
+    /// Descarta sectores angulares ocupados por el propio robot.
+    AngularMask(Vec<AngularMask>),
+    /// Conserva un haz de cada `step` (reducción angular).
+    AngleDownsample { step: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanFilterConfig {
+    pub filters: Vec<ScanFilter>,
+    /// Pose del LiDAR respecto al marco del robot (`base_link`).
+    pub sensor_pose: Isometry3<f64>,
+    /// Tamaño de vóxel (m) para reducir la nube resultante; `None` la deja completa.
+    pub voxel_size: Option<f64>,
+}
+
+impl Default for ScanFilterConfig {
+    fn default() -> Self {
+        Self {
+            filters: vec![
+                ScanFilter::InvalidRemoval,
+                ScanFilter::RangeClip { min: None, max: None },
+                ScanFilter::Median { window: 3 },
+                ScanFilter::Shadow { min_angle: 0.17, neighbors: 1 },
+            ],
+            sensor_pose: Isometry3::translation(0.0, 0.0, 0.2),
+            voxel_size: Some(0.05),
+        }
+    }
+}
+
+/// Barrido filtrado junto con su nube de puntos en el marco del robot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilteredScan {
+    pub scan: LidarData,
+    pub points: Vec<Point3<f64>>,
+    pub valid_beams: usize,
+}
+
+impl FilteredScan {
+    /// Proyección 2D de la nube (plano XY del robot).
+    pub fn points_2d(&self) -> Vec<Point2<f64>> {
+        self.points.iter().map(|p| Point2::new(p.x, p.y)).collect()
+    }
+}
+
+pub struct ScanFilterChain {
+    config: ScanFilterConfig,
+}
+
+impl ScanFilterChain {
+    pub fn new(config: ScanFilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Aplica la cadena de filtros. Las lecturas descartadas se marcan como NaN
+    /// para conservar la indexación angular del barrido.
+    pub fn apply(&self, scan: &LidarData) -> FilteredScan {
+        let mut filtered = scan.clone();
+
+        for filter in &self.config.filters {
+            match filter {
+                ScanFilter::RangeClip { min, max } => range_clip(&mut filtered, *min, *max),
+                ScanFilter::InvalidRemoval => remove_invalid(&mut filtered),
+                ScanFilter::Median { window } => median(&mut filtered, *window),
+                ScanFilter::Shadow { min_angle, neighbors } => shadow(&mut filtered, *min_angle, *neighbors),
+                ScanFilter::AngularMask(masks) => angular_mask(&mut filtered, masks),
+                ScanFilter::AngleDownsample { step } => angle_downsample(&mut filtered, *step),
+            }
+        }
+
+        let mut points = to_point_cloud(&filtered, &self.config.sensor_pose);
+        if let Some(voxel) = self.config.voxel_size {
+            points = voxel_downsample(&points, voxel);
+        }
+
+        let valid_beams = filtered.ranges.iter().filter(|r| r.is_finite()).count();
+        FilteredScan {
+            scan: filtered,
+            points,
+            valid_beams,
+        }
+    }
+}
+
+impl Default for ScanFilterChain {
+    fn default() -> Self {
+        Self::new(ScanFilterConfig::default())
+    }
+}
+
+fn beam_angle(scan: &LidarData, index: usize) -> f32 {
+    scan.angle_min + index as f32 * scan.angle_increment
+}
+
+fn range_clip(scan: &mut LidarData, min: Option<f32>, max: Option<f32>) {
+    let min = min.map_or(scan.range_min, |m| m.max(scan.range_min));
+    let max = max.map_or(scan.range_max, |m| m.min(scan.range_max));
+    for r in scan.ranges.iter_mut() {
+        if *r < min || *r > max {
+            *r = f32::NAN;
+        }
+    }
+}
+
+fn remove_invalid(scan: &mut LidarData) {
+    for r in scan.ranges.iter_mut() {
+        if !r.is_finite() || *r <= 0.0 {
+            *r = f32::NAN;
+        }
+    }
+}
+
+fn median(scan: &mut LidarData, window: usize) {
+    let half = window / 2;
+    if half == 0 {
+        return;
+    }
+
+    let source = scan.ranges.clone();
+    for (i, r) in scan.ranges.iter_mut().enumerate() {
+        if !source[i].is_finite() {
+            continue;
+        }
+        let start = i.saturating_sub(half);
+        let end = (i + half + 1).min(source.len());
+        let mut values: Vec<f32> = source[start..end].iter().copied().filter(|v| v.is_finite()).collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        *r = values[values.len() / 2];
+    }
+}
+
+fn shadow(scan: &mut LidarData, min_angle: f32, neighbors: usize) {
+    let source = scan.ranges.clone();
+    let sin_inc = scan.angle_increment.abs().sin();
+    let cos_inc = scan.angle_increment.abs().cos();
+
+    for i in 0..source.len() {
+        let r1 = source[i];
+        if !r1.is_finite() {
+            continue;
+        }
+        for offset in 1..=neighbors {
+            let Some(j) = i.checked_add(offset).filter(|&j| j < source.len()) else { break };
+            let r2 = source[j];
+            if !r2.is_finite() {
+                continue;
+            }
+
+            // Ángulo entre el haz y la recta que une ambos puntos
+            let inc_sin = sin_inc * offset as f32;
+            let angle = (r2 * inc_sin).atan2(r1 - r2 * cos_inc).abs();
+            let angle = angle.min(std::f32::consts::PI - angle);
+            if angle < min_angle {
+                // El punto más lejano es el fantasma
+                if r1 > r2 { scan.ranges[i] = f32::NAN } else { scan.ranges[j] = f32::NAN }
+            }
+        }
+    }
+}
+
+fn angular_mask(scan: &mut LidarData, masks: &[AngularMask]) {
+    for i in 0..scan.ranges.len() {
+        let angle = beam_angle(scan, i);
+        if masks.iter().any(|m| m.contains(angle)) {
+            scan.ranges[i] = f32::NAN;
+        }
+    }
+}
+
+fn angle_downsample(scan: &mut LidarData, step: usize) {
+    if step <= 1 {
+        return;
+    }
+    scan.ranges = scan.ranges.iter().step_by(step).copied().collect();
+    if !scan.intensities.is_empty() {
+        scan.intensities = scan.intensities.iter().step_by(step).copied().collect();
+    }
+    scan.angle_increment *= step as f32;
+    scan.angle_max = scan.angle_min + scan.angle_increment * scan.ranges.len().saturating_sub(1) as f32;
+}
+
+/// Convierte un barrido en puntos 3D en el marco del robot.
+pub fn to_point_cloud(scan: &LidarData, sensor_pose: &Isometry3<f64>) -> Vec<Point3<f64>> {
+    scan.ranges.iter()
+        .enumerate()
+        .filter(|(_, r)| r.is_finite())
+        .map(|(i, &r)| {
+            let angle = beam_angle(scan, i) as f64;
+            let local = Point3::new(r as f64 * angle.cos(), r as f64 * angle.sin(), 0.0);
+            sensor_pose * local
+        })
+        .collect()
+}
+
+/// Reduce la nube a un punto (centroide) por vóxel.
+pub fn voxel_downsample(points: &[Point3<f64>], voxel_size: f64) -> Vec<Point3<f64>> {
+    use std::collections::HashMap;
+
+    if voxel_size <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut voxels: HashMap<(i64, i64, i64), (Vector3<f64>, usize)> = HashMap::new();
+    let mut order = Vec::new();
+    for p in points {
+        let key = (
+            (p.x / voxel_size).floor() as i64,
+            (p.y / voxel_size).floor() as i64,
+            (p.z / voxel_size).floor() as i64,
+        );
+        let entry = voxels.entry(key).or_insert_with(|| {
+            order.push(key);
+            (Vector3::zeros(), 0)
+        });
+        entry.0 += p.coords;
+        entry.1 += 1;
+    }
+
+    order.into_iter()
+        .map(|key| {
+            let (sum, count) = voxels[&key];
+            Point3::from(sum / count as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(ranges: Vec<f32>) -> LidarData {
+        let n = ranges.len();
+        LidarData {
+            intensities: vec![100.0; n],
+            ranges,
+            angle_min: 0.0,
+            angle_max: 0.01 * (n - 1) as f32,
+            angle_increment: 0.01,
+            range_min: 0.1,
+            range_max: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_invalid_and_range_clip() {
+        let chain = ScanFilterChain::new(ScanFilterConfig {
+            filters: vec![ScanFilter::InvalidRemoval, ScanFilter::RangeClip { min: None, max: Some(5.0) }],
+            sensor_pose: Isometry3::identity(),
+            voxel_size: None,
+        });
+
+        let result = chain.apply(&scan(vec![1.0, f32::NAN, 0.05, 6.0, f32::INFINITY, 2.0]));
+        assert_eq!(result.valid_beams, 2);
+        assert_eq!(result.points.len(), 2);
+        assert_eq!(result.scan.ranges.len(), 6);
+    }
+
+    #[test]
+    fn test_median_removes_spike() {
+        let chain = ScanFilterChain::new(ScanFilterConfig {
+            filters: vec![ScanFilter::Median { window: 3 }],
+            sensor_pose: Isometry3::identity(),
+            voxel_size: None,
+        });
+
+        let result = chain.apply(&scan(vec![2.0, 2.0, 0.3, 2.0, 2.0]));
+        assert!(result.scan.ranges.iter().all(|&r| (r - 2.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_mask_and_robot_frame() {
+        let mut data = scan(vec![1.0; 4]);
+        data.angle_min = -std::f32::consts::FRAC_PI_2;
+        data.angle_increment = std::f32::consts::FRAC_PI_2;
+
+        let chain = ScanFilterChain::new(ScanFilterConfig {
+            filters: vec![ScanFilter::AngularMask(vec![AngularMask { angle_min: 3.0, angle_max: -3.0 }])],
+            sensor_pose: Isometry3::translation(0.5, 0.0, 0.2),
+            voxel_size: None,
+        });
+
+        // Haces a -90°, 0°, 90°, 180°; el de 180° cae en la máscara trasera
+        let result = chain.apply(&data);
+        assert_eq!(result.valid_beams, 3);
+        let forward = result.points[1];
+        assert!((forward.x - 1.5).abs() < 1e-6 && forward.y.abs() < 1e-6 && (forward.z - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_shadow_removes_veiling_point() {
+        let chain = ScanFilterChain::new(ScanFilterConfig {
+            filters: vec![ScanFilter::Shadow { min_angle: 0.17, neighbors: 1 }],
+            sensor_pose: Isometry3::identity(),
+            voxel_size: None,
+        });
+
+        // Borde de un objeto a 2 m con la pared a 6 m detrás: cae el primer punto lejano
+        let result = chain.apply(&scan(vec![2.0, 2.0, 2.0, 6.0, 6.0, 6.0]));
+        assert!(result.scan.ranges[3].is_nan());
+        assert_eq!(result.valid_beams, 5);
+        assert!(result.scan.ranges[..3].iter().all(|&r| r == 2.0));
+        assert!(result.scan.ranges[4..].iter().all(|&r| r == 6.0));
+    }
+
+    #[test]
+    fn test_angle_downsample_keeps_beam_geometry() {
+        let chain = ScanFilterChain::new(ScanFilterConfig {
+            filters: vec![ScanFilter::AngleDownsample { step: 3 }],
+            sensor_pose: Isometry3::identity(),
+            voxel_size: None,
+        });
+
+        let result = chain.apply(&scan((0..10).map(|i| 1.0 + i as f32).collect()));
+        assert_eq!(result.scan.ranges, vec![1.0, 4.0, 7.0, 10.0]);
+        assert_eq!(result.scan.intensities.len(), 4);
+        assert!((result.scan.angle_increment - 0.03).abs() < 1e-6);
+        assert!((result.scan.angle_max - 0.09).abs() < 1e-6);
+
+        // Cada haz conservado sigue en su ángulo original
+        let second = result.points[1];
+        assert!((second.y.atan2(second.x) - 0.03).abs() < 1e-6);
+        assert!((second.coords.norm() - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_voxel_downsample_merges_to_centroid() {
+        let points = [
+            Point3::new(0.01, 0.01, 0.0),
+            Point3::new(0.03, 0.03, 0.0),
+            Point3::new(0.2, 0.0, 0.0),
+        ];
+
+        let reduced = voxel_downsample(&points, 0.05);
+        assert_eq!(reduced.len(), 2);
+        assert!((reduced[0] - Point3::new(0.02, 0.02, 0.0)).norm() < 1e-9);
+        assert_eq!(reduced[1], points[2]);
+        assert_eq!(voxel_downsample(&points, 0.0).len(), 3);
+    }
+
+    #[test]
+    fn test_default_chain_on_simulated_scan() {
+        // Mismo barrido que el LiDAR simulado: 360 haces a 5 m
+        let beams = 360;
+        let angle_increment = 2.0 * std::f32::consts::PI / beams as f32;
+        let data = LidarData {
+            ranges: vec![5.0; beams],
+            intensities: vec![100.0; beams],
+            angle_min: -std::f32::consts::PI,
+            angle_max: std::f32::consts::PI - angle_increment,
+            angle_increment,
+            range_min: 0.1,
+            range_max: 12.0,
+        };
+
+        // Círculo liso: ni la mediana ni el filtro de sombras descartan nada y los
+        // haces (~8.7 cm entre sí) caen en vóxeles distintos
+        let result = ScanFilterChain::default().apply(&data);
+        assert_eq!(result.valid_beams, beams);
+        assert_eq!(result.points.len(), beams);
+        assert!(result.points.iter().all(|p| (p.xy().coords.norm() - 5.0).abs() < 1e-4 && (p.z - 0.2).abs() < 1e-9));
+    }
+}
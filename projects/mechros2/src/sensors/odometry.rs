@@ -0,0 +1,225 @@
+// 🛞 Wheel Odometry Module
+// File: projects/mechros2/src/sensors/odometry.rs
+
+use std::time::Instant;
+use nalgebra::{Isometry3, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use super::orientation::wrap_angle;
+
+/// Número de ruedas con encoder; mismo orden que los motores de `MotorController`.
+pub const WHEEL_COUNT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DriveKinematics {
+    /// Diferencial (skid-steer): las ruedas de cada lado se promedian.
+    Differential,
+    /// Mecanum en configuración "X": permite desplazamiento lateral.
+    Mecanum,
+}
+
+#[derive(Debug, Clone)]
+pub struct OdometryConfig {
+    pub kinematics: DriveKinematics,
+    pub wheel_radius: f64,    // m
+    pub track_width: f64,     // Distancia entre ruedas izquierda y derecha (m)
+    pub wheelbase: f64,       // Distancia entre ejes delantero y trasero (m)
+    pub ticks_per_revolution: f64,
+}
+
+impl Default for OdometryConfig {
+    fn default() -> Self {
+        Self {
+            kinematics: DriveKinematics::Differential,
+            wheel_radius: 0.08,
+            track_width: 0.4,
+            wheelbase: 0.4,
+            ticks_per_revolution: 2048.0,
+        }
+    }
+}
+
+/// Contadores acumulados de los encoders: [delantera izq., delantera der., trasera izq., trasera der.].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderTicks(pub [i32; WHEEL_COUNT]);
+
+/// Pose y velocidad estimadas por navegación a estima, en el marco `odom`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OdometryEstimate {
+    pub position: Point3<f64>,
+    pub heading: f64,
+    pub linear_velocity: Vector3<f64>, // Marco del robot
+    pub angular_velocity: f64,
+    pub distance_traveled: f64,
+}
+
+impl OdometryEstimate {
+    /// Pose del robot en el marco `odom`.
+    pub fn pose(&self) -> Isometry3<f64> {
+        Isometry3::new(self.position.coords, Vector3::z() * self.heading)
+    }
+
+    /// Velocidad lineal expresada en el marco `odom`.
+    pub fn world_velocity(&self) -> Vector3<f64> {
+        let (sin, cos) = self.heading.sin_cos();
+        Vector3::new(
+            self.linear_velocity.x * cos - self.linear_velocity.y * sin,
+            self.linear_velocity.x * sin + self.linear_velocity.y * cos,
+            0.0,
+        )
+    }
+}
+
+pub struct WheelOdometry {
+    config: OdometryConfig,
+    last_ticks: Option<EncoderTicks>,
+    last_update: Option<Instant>,
+    x: f64,
+    y: f64,
+    heading: f64,
+    distance_traveled: f64,
+}
+
+impl WheelOdometry {
+    pub fn new(config: OdometryConfig) -> Self {
+        Self {
+            config,
+            last_ticks: None,
+            last_update: None,
+            x: 0.0,
+            y: 0.0,
+            heading: 0.0,
+            distance_traveled: 0.0,
+        }
+    }
+
+    pub fn config(&self) -> &OdometryConfig {
+        &self.config
+    }
+
+    /// Reinicia la pose estimada (p. ej. al recibir una corrección externa).
+    pub fn reset(&mut self, x: f64, y: f64, heading: f64) {
+        self.x = x;
+        self.y = y;
+        self.heading = heading;
+    }
+
+    /// Actualiza usando el reloj monotónico para calcular `dt`.
+    pub fn update_at(&mut self, ticks: EncoderTicks, now: Instant) -> OdometryEstimate {
+        let dt = self.last_update.map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_update = Some(now);
+        self.update(ticks, dt)
+    }
+
+    pub fn update(&mut self, ticks: EncoderTicks, dt: f64) -> OdometryEstimate {
+        // La primera lectura solo fija la referencia de los contadores
+        let previous = self.last_ticks.replace(ticks).unwrap_or(ticks);
+
+        let meters_per_tick = 2.0 * std::f64::consts::PI * self.config.wheel_radius / self.config.ticks_per_revolution;
+        let mut wheel = [0.0; WHEEL_COUNT];
+        for (i, distance) in wheel.iter_mut().enumerate() {
+            // Resta con desbordamiento: los contadores del hardware son de 32 bits
+            *distance = ticks.0[i].wrapping_sub(previous.0[i]) as f64 * meters_per_tick;
+        }
+
+        let (dx, dy, dtheta) = self.body_displacement(&wheel);
+
+        // Integración con el rumbo en el punto medio del intervalo
+        let mid_heading = self.heading + dtheta / 2.0;
+        let (sin, cos) = mid_heading.sin_cos();
+        self.x += dx * cos - dy * sin;
+        self.y += dx * sin + dy * cos;
+        self.heading = wrap_angle(self.heading + dtheta);
+        self.distance_traveled += dx.hypot(dy);
+
+        let (linear_velocity, angular_velocity) = if dt > 0.0 {
+            (Vector3::new(dx / dt, dy / dt, 0.0), dtheta / dt)
+        } else {
+            (Vector3::zeros(), 0.0)
+        };
+
+        OdometryEstimate {
+            position: Point3::new(self.x, self.y, 0.0),
+            heading: self.heading,
+            linear_velocity,
+            angular_velocity,
+            distance_traveled: self.distance_traveled,
+        }
+    }
+
+    /// Desplazamiento del chasis (marco del robot) a partir del recorrido de cada rueda.
+    fn body_displacement(&self, wheel: &[f64; WHEEL_COUNT]) -> (f64, f64, f64) {
+        let [fl, fr, rl, rr] = *wheel;
+
+        match self.config.kinematics {
+            DriveKinematics::Differential => {
+                let left = (fl + rl) / 2.0;
+                let right = (fr + rr) / 2.0;
+                ((left + right) / 2.0, 0.0, (right - left) / self.config.track_width)
+            }
+            DriveKinematics::Mecanum => {
+                let lever = (self.config.track_width + self.config.wheelbase) / 2.0;
+                (
+                    (fl + fr + rl + rr) / 4.0,
+                    (-fl + fr + rl - rr) / 4.0,
+                    (-fl + fr - rl + rr) / (4.0 * lever),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn ticks_for(distance: f64, config: &OdometryConfig) -> i32 {
+        (distance / (2.0 * std::f64::consts::PI * config.wheel_radius) * config.ticks_per_revolution).round() as i32
+    }
+
+    #[test]
+    fn test_differential_straight_and_turn() {
+        let config = OdometryConfig::default();
+        let one_meter = ticks_for(1.0, &config);
+        let mut odometry = WheelOdometry::new(config.clone());
+
+        odometry.update(EncoderTicks([0; 4]), 0.0);
+        let estimate = odometry.update(EncoderTicks([one_meter; 4]), 2.0);
+        assert_relative_eq!(estimate.position.x, 1.0, epsilon = 1e-3);
+        assert_relative_eq!(estimate.position.y, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(estimate.linear_velocity.x, 0.5, epsilon = 1e-3);
+
+        // Giro en el sitio de 90°: cada lado recorre un cuarto de la circunferencia del ancho de vía
+        let arc = ticks_for(std::f64::consts::FRAC_PI_2 * config.track_width / 2.0, &config);
+        let estimate = odometry.update(EncoderTicks([one_meter - arc, one_meter + arc, one_meter - arc, one_meter + arc]), 1.0);
+        assert_relative_eq!(estimate.heading, std::f64::consts::FRAC_PI_2, epsilon = 1e-3);
+        assert_relative_eq!(estimate.position.x, 1.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_mecanum_strafe() {
+        let config = OdometryConfig {
+            kinematics: DriveKinematics::Mecanum,
+            ..OdometryConfig::default()
+        };
+        let d = ticks_for(0.5, &config);
+        let mut odometry = WheelOdometry::new(config);
+
+        odometry.update(EncoderTicks([0; 4]), 0.0);
+        let estimate = odometry.update(EncoderTicks([-d, d, d, -d]), 1.0);
+        assert_relative_eq!(estimate.position.x, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(estimate.position.y, 0.5, epsilon = 1e-3);
+        assert_relative_eq!(estimate.heading, 0.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_counter_wraparound() {
+        let mut odometry = WheelOdometry::new(OdometryConfig::default());
+        odometry.update(EncoderTicks([i32::MAX - 10; 4]), 0.0);
+        let estimate = odometry.update(EncoderTicks([i32::MIN + 9; 4]), 0.1);
+
+        // 20 ticks hacia delante, no una vuelta completa del contador
+        let expected = 20.0 * 2.0 * std::f64::consts::PI * 0.08 / 2048.0;
+        assert_relative_eq!(estimate.position.x, expected, epsilon = 1e-9);
+    }
+}
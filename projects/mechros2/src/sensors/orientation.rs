@@ -0,0 +1,347 @@
+// 🧭 Orientation Filter Module
+// File: projects/mechros2/src/sensors/orientation.rs
+
+use std::time::Instant;
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Ángulos de Euler en radianes (convención ROS: roll sobre X, pitch sobre Y, yaw sobre Z).
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct EulerAngles {
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+impl From<EulerAngles> for UnitQuaternion<f64> {
+    fn from(angles: EulerAngles) -> Self {
+        UnitQuaternion::from_euler_angles(angles.roll, angles.pitch, angles.yaw)
+    }
+}
+
+/// Acceso a roll/pitch/yaw desde la representación en cuaterniones.
+pub trait RollPitchYaw {
+    fn roll(&self) -> f64;
+    fn pitch(&self) -> f64;
+    fn yaw(&self) -> f64;
+    fn to_euler(&self) -> EulerAngles;
+}
+
+impl RollPitchYaw for UnitQuaternion<f64> {
+    fn roll(&self) -> f64 {
+        self.euler_angles().0
+    }
+
+    fn pitch(&self) -> f64 {
+        self.euler_angles().1
+    }
+
+    fn yaw(&self) -> f64 {
+        self.euler_angles().2
+    }
+
+    fn to_euler(&self) -> EulerAngles {
+        let (roll, pitch, yaw) = self.euler_angles();
+        EulerAngles { roll, pitch, yaw }
+    }
+}
+
+/// Ángulo equivalente en [-π, π); único helper de normalización para rumbos y errores angulares.
+pub fn wrap_angle(angle: f64) -> f64 {
+    (angle + std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI) - std::f64::consts::PI
+}
+
+/// Serializa un cuaternión como `{roll, pitch, yaw}` (contrato del dashboard vía rosbridge).
+pub mod rpy_serde {
+    use nalgebra::UnitQuaternion;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{EulerAngles, RollPitchYaw};
+
+    pub fn serialize<S: Serializer>(q: &UnitQuaternion<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        q.to_euler().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<UnitQuaternion<f64>, D::Error> {
+        EulerAngles::deserialize(deserializer).map(UnitQuaternion::from)
+    }
+}
+
+/// Como `rpy_serde`, para campos opcionales.
+pub mod rpy_serde_option {
+    use nalgebra::UnitQuaternion;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{EulerAngles, RollPitchYaw};
+
+    pub fn serialize<S: Serializer>(q: &Option<UnitQuaternion<f64>>, serializer: S) -> Result<S::Ok, S::Error> {
+        q.map(|q| q.to_euler()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<UnitQuaternion<f64>>, D::Error> {
+        Option::<EulerAngles>::deserialize(deserializer).map(|angles| angles.map(UnitQuaternion::from))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrientationFilterConfig {
+    pub beta: f64,            // Ganancia del gradiente de Madgwick
+    pub max_dt: f64,          // s; huecos mayores reinician la integración
+    pub use_magnetometer: bool,
+}
+
+impl Default for OrientationFilterConfig {
+    fn default() -> Self {
+        Self {
+            beta: 0.1,
+            max_dt: 0.5,
+            use_magnetometer: true,
+        }
+    }
+}
+
+/// Filtro de actitud de Madgwick (IMU y MARG). El cuaternión resultante rota del
+/// marco del robot al marco mundo; con magnetómetro el yaw se referencia al norte
+/// magnético, sin él es relativo a la orientación inicial.
+#[derive(Debug, Clone)]
+pub struct MadgwickFilter {
+    config: OrientationFilterConfig,
+    q: Quaternion<f64>,
+    initialized: bool,
+    last_update: Option<Instant>,
+}
+
+impl MadgwickFilter {
+    pub fn new(config: OrientationFilterConfig) -> Self {
+        Self {
+            config,
+            q: Quaternion::identity(),
+            initialized: false,
+            last_update: None,
+        }
+    }
+
+    pub fn orientation(&self) -> UnitQuaternion<f64> {
+        UnitQuaternion::new_normalize(self.q)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    pub fn reset(&mut self) {
+        self.q = Quaternion::identity();
+        self.initialized = false;
+        self.last_update = None;
+    }
+
+    /// Actualiza usando el reloj monotónico para calcular `dt`.
+    pub fn update_at(&mut self, gyro: &Vector3<f64>, accel: &Vector3<f64>, mag: Option<&Vector3<f64>>, now: Instant) -> UnitQuaternion<f64> {
+        let dt = self.last_update.map(|last| now.duration_since(last).as_secs_f64());
+        self.last_update = Some(now);
+
+        match dt {
+            Some(dt) if dt > 0.0 && dt <= self.config.max_dt => self.update(gyro, accel, mag, dt),
+            _ => {
+                // Primera muestra o hueco demasiado largo: reinicializar desde la gravedad
+                self.initialized = false;
+                self.update(gyro, accel, mag, 0.0)
+            }
+        }
+    }
+
+    pub fn update(&mut self, gyro: &Vector3<f64>, accel: &Vector3<f64>, mag: Option<&Vector3<f64>>, dt: f64) -> UnitQuaternion<f64> {
+        let mag = mag.filter(|m| self.config.use_magnetometer && m.norm() > 0.0);
+
+        if !self.initialized {
+            if let Some(q) = initial_orientation(accel, mag) {
+                self.q = q.into_inner();
+                self.initialized = true;
+            }
+            return self.orientation();
+        }
+
+        let q = self.q;
+        let (q0, q1, q2, q3) = (q.w, q.i, q.j, q.k);
+
+        // Derivada por integración del giroscopio: q̇ = ½ q ⊗ ω
+        let omega = Quaternion::new(0.0, gyro.x, gyro.y, gyro.z);
+        let mut q_dot = (q * omega) * 0.5;
+
+        let accel_norm = accel.norm();
+        if accel_norm > 0.0 {
+            let a = accel / accel_norm;
+
+            let step = match mag {
+                Some(m) => {
+                    let m = m.normalize();
+
+                    // Dirección del campo en el marco mundo (componentes horizontal y vertical)
+                    let h = q * Quaternion::new(0.0, m.x, m.y, m.z) * q.conjugate();
+                    let bx = (h.i * h.i + h.j * h.j).sqrt();
+                    let bz = h.k;
+
+                    let f = [
+                        2.0 * (q1 * q3 - q0 * q2) - a.x,
+                        2.0 * (q0 * q1 + q2 * q3) - a.y,
+                        2.0 * (0.5 - q1 * q1 - q2 * q2) - a.z,
+                        2.0 * bx * (0.5 - q2 * q2 - q3 * q3) + 2.0 * bz * (q1 * q3 - q0 * q2) - m.x,
+                        2.0 * bx * (q1 * q2 - q0 * q3) + 2.0 * bz * (q0 * q1 + q2 * q3) - m.y,
+                        2.0 * bx * (q0 * q2 + q1 * q3) + 2.0 * bz * (0.5 - q1 * q1 - q2 * q2) - m.z,
+                    ];
+
+                    // Jacobiano transpuesto por la función objetivo
+                    Quaternion::new(
+                        -2.0 * q2 * f[0] + 2.0 * q1 * f[1]
+                            - 2.0 * bz * q2 * f[3]
+                            + (-2.0 * bx * q3 + 2.0 * bz * q1) * f[4]
+                            + 2.0 * bx * q2 * f[5],
+                        2.0 * q3 * f[0] + 2.0 * q0 * f[1] - 4.0 * q1 * f[2]
+                            + 2.0 * bz * q3 * f[3]
+                            + (2.0 * bx * q2 + 2.0 * bz * q0) * f[4]
+                            + (2.0 * bx * q3 - 4.0 * bz * q1) * f[5],
+                        -2.0 * q0 * f[0] + 2.0 * q3 * f[1] - 4.0 * q2 * f[2]
+                            + (-4.0 * bx * q2 - 2.0 * bz * q0) * f[3]
+                            + (2.0 * bx * q1 + 2.0 * bz * q3) * f[4]
+                            + (2.0 * bx * q0 - 4.0 * bz * q2) * f[5],
+                        2.0 * q1 * f[0] + 2.0 * q2 * f[1]
+                            + (-4.0 * bx * q3 + 2.0 * bz * q1) * f[3]
+                            + (-2.0 * bx * q0 + 2.0 * bz * q2) * f[4]
+                            + 2.0 * bx * q1 * f[5],
+                    )
+                }
+                None => {
+                    let f = [
+                        2.0 * (q1 * q3 - q0 * q2) - a.x,
+                        2.0 * (q0 * q1 + q2 * q3) - a.y,
+                        2.0 * (0.5 - q1 * q1 - q2 * q2) - a.z,
+                    ];
+
+                    Quaternion::new(
+                        -2.0 * q2 * f[0] + 2.0 * q1 * f[1],
+                        2.0 * q3 * f[0] + 2.0 * q0 * f[1] - 4.0 * q1 * f[2],
+                        -2.0 * q0 * f[0] + 2.0 * q3 * f[1] - 4.0 * q2 * f[2],
+                        2.0 * q1 * f[0] + 2.0 * q2 * f[1],
+                    )
+                }
+            };
+
+            let step_norm = step.norm();
+            if step_norm > 0.0 {
+                q_dot -= step * (self.config.beta / step_norm);
+            }
+        }
+
+        self.q = (q + q_dot * dt).normalize();
+        self.orientation()
+    }
+}
+
+impl Default for MadgwickFilter {
+    fn default() -> Self {
+        Self::new(OrientationFilterConfig::default())
+    }
+}
+
+/// Orientación inicial a partir de la gravedad y, si está disponible, del campo magnético.
+pub fn initial_orientation(accel: &Vector3<f64>, mag: Option<&Vector3<f64>>) -> Option<UnitQuaternion<f64>> {
+    if accel.norm() <= 0.0 {
+        return None;
+    }
+
+    let roll = accel.y.atan2(accel.z);
+    let pitch = (-accel.x).atan2((accel.y * accel.y + accel.z * accel.z).sqrt());
+
+    let yaw = mag.map_or(0.0, |m| super::compass::tilt_compensated_yaw(m, roll, pitch));
+
+    Some(UnitQuaternion::from_euler_angles(roll, pitch, yaw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const G: f64 = 9.81;
+
+    #[test]
+    fn test_initial_orientation_from_gravity() {
+        let roll = 0.3_f64;
+        let accel = Vector3::new(0.0, G * roll.sin(), G * roll.cos());
+        let q = initial_orientation(&accel, None).unwrap();
+
+        assert_relative_eq!(q.roll(), roll, epsilon = 1e-9);
+        assert_relative_eq!(q.pitch(), 0.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_gyro_integration_yaw() {
+        let mut filter = MadgwickFilter::default();
+        let accel = Vector3::new(0.0, 0.0, G);
+        let gyro = Vector3::new(0.0, 0.0, 0.5);
+
+        filter.update(&gyro, &accel, None, 0.0);
+        for _ in 0..100 {
+            filter.update(&gyro, &accel, None, 0.01);
+        }
+
+        assert_relative_eq!(filter.orientation().yaw(), 0.5, epsilon = 1e-3);
+        assert_relative_eq!(filter.orientation().roll(), 0.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_accel_correction_converges() {
+        let mut filter = MadgwickFilter::default();
+        filter.update(&Vector3::zeros(), &Vector3::new(0.0, 0.0, G), None, 0.0);
+
+        // Inclinación real de 0.2 rad en pitch, sin giro medido
+        let pitch = 0.2_f64;
+        let accel = Vector3::new(-G * pitch.sin(), 0.0, G * pitch.cos());
+        for _ in 0..2000 {
+            filter.update(&Vector3::zeros(), &accel, None, 0.01);
+        }
+
+        assert_relative_eq!(filter.orientation().pitch(), pitch, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn test_magnetometer_heading() {
+        let yaw = 0.7_f64;
+        // Campo apuntando al norte con inclinación hacia abajo, visto desde el robot girado
+        let field_world = Vector3::new(0.2, 0.0, -0.4);
+        let body = UnitQuaternion::from_euler_angles(0.0, 0.0, yaw);
+        let mag = body.inverse() * field_world;
+
+        let mut filter = MadgwickFilter::default();
+        let accel = Vector3::new(0.0, 0.0, G);
+        filter.update(&Vector3::zeros(), &accel, Some(&mag), 0.0);
+        for _ in 0..500 {
+            filter.update(&Vector3::zeros(), &accel, Some(&mag), 0.01);
+        }
+
+        assert_relative_eq!(filter.orientation().yaw(), yaw, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_wrap_angle() {
+        use std::f64::consts::PI;
+        assert_relative_eq!(wrap_angle(0.5), 0.5);
+        assert_relative_eq!(wrap_angle(3.0 * PI / 2.0), -PI / 2.0, epsilon = 1e-12);
+        assert_relative_eq!(wrap_angle(-5.0 * PI / 2.0), -PI / 2.0, epsilon = 1e-12);
+        assert_relative_eq!(wrap_angle(PI), -PI, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_rpy_serialization() {
+        #[derive(Serialize, Deserialize)]
+        struct State {
+            #[serde(with = "rpy_serde")]
+            orientation: UnitQuaternion<f64>,
+        }
+
+        let state = State { orientation: UnitQuaternion::from_euler_angles(0.1, -0.2, 1.0) };
+        let json = serde_json::to_value(&state).unwrap();
+        assert_relative_eq!(json["orientation"]["yaw"].as_f64().unwrap(), 1.0, epsilon = 1e-9);
+
+        let back: State = serde_json::from_value(json).unwrap();
+        assert_relative_eq!(back.orientation.pitch(), -0.2, epsilon = 1e-9);
+    }
+}
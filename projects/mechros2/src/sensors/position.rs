@@ -0,0 +1,137 @@
+// 📍 Position Fusion Module
+// File: projects/mechros2/src/sensors/position.rs
+//
+// La posición publicada vive siempre en el marco `odom` (metros). La odometría
+// manda cuando está disponible; sin ella, el fix GPS se proyecta al plano
+// tangente local y se ancla a la última pose en la que ambos coincidieron, de
+// modo que perder o recuperar cualquiera de las dos fuentes no provoca saltos
+// entre marcos ni unidades. El fix GPS crudo se sigue publicando aparte.
+
+use nalgebra::{Point3, Rotation3, Vector3};
+use super::GpsData;
+use super::odometry::OdometryEstimate;
+
+/// Radio medio terrestre (m); basta para la proyección local de un robot terrestre.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+#[derive(Debug, Clone)]
+pub struct PositionConfig {
+    /// Giro del marco `odom` respecto a ENU (rad): 0 = x al este, y al norte.
+    pub odom_yaw_in_enu: f64,
+}
+
+impl Default for PositionConfig {
+    fn default() -> Self {
+        Self { odom_yaw_in_enu: 0.0 }
+    }
+}
+
+/// Fix GPS y posición `odom` tomados en el mismo ciclo.
+#[derive(Debug, Clone)]
+struct GpsAnchor {
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+    odom: Point3<f64>,
+}
+
+pub struct PositionFuser {
+    config: PositionConfig,
+    anchor: Option<GpsAnchor>,
+}
+
+impl PositionFuser {
+    pub fn new(config: PositionConfig) -> Self {
+        Self { config, anchor: None }
+    }
+
+    /// Posición en el marco `odom`; `None` si no hay odometría ni un GPS anclado.
+    pub fn update(&mut self, gps: Option<&GpsData>, odometry: Option<&OdometryEstimate>) -> Option<Point3<f64>> {
+        match (gps, odometry) {
+            (gps, Some(odom)) => {
+                if let Some(gps) = gps {
+                    self.anchor = Some(GpsAnchor {
+                        latitude: gps.latitude,
+                        longitude: gps.longitude,
+                        altitude: gps.altitude,
+                        odom: odom.position,
+                    });
+                }
+                Some(odom.position)
+            }
+            (Some(gps), None) => self.anchor.as_ref().map(|anchor| anchor.odom + self.odom_offset(anchor, gps)),
+            (None, None) => None,
+        }
+    }
+
+    /// Desplazamiento del fix respecto al ancla, en el marco `odom`.
+    fn odom_offset(&self, anchor: &GpsAnchor, gps: &GpsData) -> Vector3<f64> {
+        let east = (gps.longitude - anchor.longitude).to_radians() * EARTH_RADIUS * anchor.latitude.to_radians().cos();
+        let north = (gps.latitude - anchor.latitude).to_radians() * EARTH_RADIUS;
+        let enu = Vector3::new(east, north, gps.altitude - anchor.altitude);
+        Rotation3::from_axis_angle(&Vector3::z_axis(), -self.config.odom_yaw_in_enu) * enu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gps(latitude: f64, longitude: f64) -> GpsData {
+        GpsData {
+            latitude,
+            longitude,
+            altitude: 650.0,
+            speed: 0.0,
+            heading: 0.0,
+            satellites: 8,
+            accuracy: 2.0,
+            fix_time: None,
+        }
+    }
+
+    fn odometry(x: f64, y: f64) -> OdometryEstimate {
+        OdometryEstimate {
+            position: Point3::new(x, y, 0.0),
+            heading: 0.0,
+            linear_velocity: Vector3::zeros(),
+            angular_velocity: 0.0,
+            distance_traveled: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_gps_dropout_keeps_odom_frame() {
+        let mut fuser = PositionFuser::new(PositionConfig::default());
+        let fix = gps(40.4168, -3.7038);
+
+        let with_gps = fuser.update(Some(&fix), Some(&odometry(1.0, 2.0))).unwrap();
+        assert_eq!(with_gps, Point3::new(1.0, 2.0, 0.0));
+
+        // Se pierde el GPS a mitad de recorrido: la posición sigue en metros `odom`
+        let without_gps = fuser.update(None, Some(&odometry(1.5, 2.0))).unwrap();
+        assert_eq!(without_gps, Point3::new(1.5, 2.0, 0.0));
+
+        // Y al recuperarlo no salta a latitud/longitud
+        let recovered = fuser.update(Some(&fix), Some(&odometry(2.0, 2.0))).unwrap();
+        assert_eq!(recovered, Point3::new(2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn test_gps_projected_from_anchor_without_odometry() {
+        let mut fuser = PositionFuser::new(PositionConfig::default());
+        assert!(fuser.update(Some(&gps(40.0, -3.0)), None).is_none()); // Sin ancla todavía
+
+        fuser.update(Some(&gps(40.0, -3.0)), Some(&odometry(5.0, 5.0)));
+        // 10 m al norte: Δlat = 10 / R rad
+        let north = gps(40.0 + (10.0 / EARTH_RADIUS).to_degrees(), -3.0);
+        let position = fuser.update(Some(&north), None).unwrap();
+        assert!((position - Point3::new(5.0, 15.0, 0.0)).norm() < 1e-6);
+
+        // Con `odom` girado 90° (x al norte) el mismo desplazamiento cae en +x
+        let mut rotated = PositionFuser::new(PositionConfig { odom_yaw_in_enu: std::f64::consts::FRAC_PI_2 });
+        rotated.update(Some(&gps(40.0, -3.0)), Some(&odometry(0.0, 0.0)));
+        let position = rotated.update(Some(&north), None).unwrap();
+        assert!((position - Point3::new(10.0, 0.0, 0.0)).norm() < 1e-6);
+    }
+}
@@ -0,0 +1,143 @@
+// 📏 Proximity Sensor Array Module
+// File: projects/mechros2/src/sensors/proximity.rs
+
+use nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProximitySensorKind {
+    Ultrasonic,
+    Infrared,
+    TimeOfFlight,
+}
+
+/// Descripción de un sensor de proximidad montado en el robot (marco `base_link`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProximitySensorConfig {
+    pub id: String,
+    pub kind: ProximitySensorKind,
+    pub mount_position: Point3<f64>,
+    pub yaw: f64,           // Dirección del eje del sensor (rad)
+    pub field_of_view: f64, // Apertura total del cono (rad)
+    pub min_range: f32,
+    pub max_range: f32,
+}
+
+impl ProximitySensorConfig {
+    pub fn direction(&self) -> Vector3<f64> {
+        Vector3::new(self.yaw.cos(), self.yaw.sin(), 0.0)
+    }
+
+    /// Una lectura es detección si no llega al máximo (sin eco). Por debajo del
+    /// mínimo el objeto está pegado al sensor: también es detección.
+    pub fn is_detection(&self, range: f32) -> bool {
+        range.is_finite() && range < self.max_range
+    }
+
+    /// Distancia utilizable: las lecturas por debajo del mínimo se sitúan en él.
+    pub fn effective_range(&self, range: f32) -> f32 {
+        range.max(self.min_range)
+    }
+}
+
+/// Lectura etiquetada y localizada de un sensor de proximidad.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProximityReading {
+    pub sensor_id: String,
+    pub kind: ProximitySensorKind,
+    pub range: f32,
+    /// Punto detectado en el marco del robot, `None` si no hay detección.
+    pub detection: Option<Point3<f64>>,
+    /// Ancho del cono a la distancia medida (m).
+    pub footprint: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProximityArrayConfig {
+    pub sensors: Vec<ProximitySensorConfig>,
+}
+
+impl ProximityArrayConfig {
+    pub fn locate(&self, ranges: &[f32]) -> Vec<ProximityReading> {
+        self.sensors.iter()
+            .zip(ranges)
+            .map(|(sensor, &raw)| {
+                let range = if raw.is_finite() { sensor.effective_range(raw) } else { raw };
+                let detection = sensor.is_detection(range)
+                    .then(|| sensor.mount_position + sensor.direction() * range as f64);
+                let footprint = 2.0 * range.max(0.0) as f64 * (sensor.field_of_view / 2.0).tan();
+
+                ProximityReading {
+                    sensor_id: sensor.id.clone(),
+                    kind: sensor.kind,
+                    range,
+                    detection,
+                    footprint,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ProximityArrayConfig {
+    fn default() -> Self {
+        use ProximitySensorKind::*;
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_6, PI};
+
+        let sensor = |id: &str, kind, x, y, yaw: f64| {
+            let (field_of_view, min_range, max_range) = match kind {
+                Ultrasonic => (0.5, 0.02, 4.0),
+                Infrared => (0.1, 0.1, 0.8),
+                TimeOfFlight => (0.44, 0.03, 2.0),
+            };
+            ProximitySensorConfig {
+                id: id.to_string(),
+                kind,
+                mount_position: Point3::new(x, y, 0.15),
+                yaw,
+                field_of_view,
+                min_range,
+                max_range,
+            }
+        };
+
+        // Chasis de 0.6 x 0.45 m
+        Self {
+            sensors: vec![
+                sensor("front_center", TimeOfFlight, 0.3, 0.0, 0.0),
+                sensor("front_left", Ultrasonic, 0.3, 0.2, FRAC_PI_6),
+                sensor("front_right", Ultrasonic, 0.3, -0.2, -FRAC_PI_6),
+                sensor("left", Infrared, 0.0, 0.225, FRAC_PI_2),
+                sensor("right", Infrared, 0.0, -0.225, -FRAC_PI_2),
+                sensor("rear_left", Ultrasonic, -0.3, 0.2, PI - FRAC_PI_6),
+                sensor("rear_right", Ultrasonic, -0.3, -0.2, -PI + FRAC_PI_6),
+                sensor("rear_center", TimeOfFlight, -0.3, 0.0, PI),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_readings_in_robot_frame() {
+        let array = ProximityArrayConfig::default();
+        let mut ranges: Vec<f32> = array.sensors.iter().map(|s| s.max_range).collect();
+        ranges[0] = 0.5; // front_center
+        ranges[3] = 0.05; // left, por debajo del mínimo del IR: objeto pegado
+
+        let readings = array.locate(&ranges);
+        assert_eq!(readings.len(), 8);
+
+        let front = readings[0].detection.unwrap();
+        assert!((front.x - 0.8).abs() < 1e-6 && front.y.abs() < 1e-6);
+        // Se informa como detección en el rango mínimo
+        let left = readings[3].detection.unwrap();
+        let min_range = array.sensors[3].min_range;
+        assert_eq!(readings[3].range, min_range);
+        assert!((left.y - (0.225 + min_range as f64)).abs() < 1e-6);
+        assert!(readings.iter().enumerate().skip(1).filter(|(i, _)| *i != 3).all(|(_, r)| r.detection.is_none()));
+    }
+}
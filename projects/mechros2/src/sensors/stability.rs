@@ -0,0 +1,252 @@
+// 🚨 Stability & Impact Detection Module
+// File: projects/mechros2/src/sensors/stability.rs
+
+use std::collections::HashMap;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use super::acquisition::Sample;
+use super::odometry::OdometryEstimate;
+use super::ImuData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IncidentKind {
+    TipOver,   // Roll o pitch excesivos
+    FreeFall,  // Aceleración total cercana a cero
+    Collision, // Pico de aceleración respecto a la referencia filtrada
+}
+
+#[derive(Debug, Clone)]
+pub struct StabilityConfig {
+    pub max_roll_deg: f64,
+    pub max_pitch_deg: f64,
+    pub tilt_duration_s: f64,       // Tiempo mínimo fuera de límites antes de disparar
+    pub free_fall_threshold: f64,   // m/s²
+    pub free_fall_duration_s: f64,
+    pub collision_threshold: f64,   // Desviación respecto a la referencia (m/s²)
+    pub baseline_time_constant_s: f64, // Filtro paso bajo de la referencia (gravedad + maniobra)
+    pub cooldown_s: f64,            // Tiempo mínimo entre incidentes del mismo tipo
+    /// Ventana previa adjunta al incidente; limitada por la capacidad de los búferes de adquisición.
+    pub pre_window_s: f64,
+}
+
+impl Default for StabilityConfig {
+    fn default() -> Self {
+        Self {
+            max_roll_deg: 30.0,
+            max_pitch_deg: 30.0,
+            tilt_duration_s: 0.2,
+            free_fall_threshold: 3.0,
+            free_fall_duration_s: 0.08,
+            collision_threshold: 15.0,
+            baseline_time_constant_s: 0.5,
+            cooldown_s: 2.0,
+            pre_window_s: 0.5,
+        }
+    }
+}
+
+/// Detección puntual, sin la ventana de datos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncidentDetection {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub kind: IncidentKind,
+    pub value: f64,     // Grados para vuelco, m/s² para caída libre y colisión
+    pub threshold: f64,
+}
+
+/// Incidente registrado con las muestras que lo precedieron.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub detection: IncidentDetection,
+    pub imu: Vec<Sample<ImuData>>,
+    pub odometry: Vec<Sample<OdometryEstimate>>,
+}
+
+pub struct StabilityMonitor {
+    config: StabilityConfig,
+    last_sequence: Option<u64>,
+    last_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    baseline: Option<Vector3<f64>>,
+    tilt_since: Option<chrono::DateTime<chrono::Utc>>,
+    free_fall_since: Option<chrono::DateTime<chrono::Utc>>,
+    last_incident: HashMap<IncidentKind, chrono::DateTime<chrono::Utc>>,
+}
+
+impl StabilityMonitor {
+    pub fn new(config: StabilityConfig) -> Self {
+        Self {
+            config,
+            last_sequence: None,
+            last_timestamp: None,
+            baseline: None,
+            tilt_since: None,
+            free_fall_since: None,
+            last_incident: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &StabilityConfig {
+        &self.config
+    }
+
+    /// Procesa las muestras de IMU aún no vistas (por número de secuencia), de
+    /// modo que los picos breves no se pierden aunque se consulte a menor frecuencia.
+    pub fn process(&mut self, samples: &[Sample<ImuData>]) -> Vec<IncidentDetection> {
+        let mut detections = Vec::new();
+        for sample in samples {
+            if self.last_sequence.is_some_and(|last| sample.sequence <= last) {
+                continue;
+            }
+            self.last_sequence = Some(sample.sequence);
+            detections.extend(self.update(&sample.value, sample.timestamp));
+        }
+        detections
+    }
+
+    pub fn update(&mut self, imu: &ImuData, timestamp: chrono::DateTime<chrono::Utc>) -> Vec<IncidentDetection> {
+        let dt = self.last_timestamp
+            .map_or(0.0, |last| (timestamp - last).num_microseconds().unwrap_or(0) as f64 / 1e6)
+            .max(0.0);
+        self.last_timestamp = Some(timestamp);
+
+        let mut candidates = Vec::new();
+        let accel = imu.linear_acceleration;
+
+        // Vuelco: inclinación sostenida fuera de límites
+        let (roll, pitch, _) = imu.orientation.euler_angles();
+        let roll_excess = roll.to_degrees().abs() / self.config.max_roll_deg;
+        let pitch_excess = pitch.to_degrees().abs() / self.config.max_pitch_deg;
+        if roll_excess > 1.0 || pitch_excess > 1.0 {
+            let since = *self.tilt_since.get_or_insert(timestamp);
+            if seconds(timestamp - since) >= self.config.tilt_duration_s {
+                let (value, threshold) = if roll_excess >= pitch_excess {
+                    (roll.to_degrees(), self.config.max_roll_deg)
+                } else {
+                    (pitch.to_degrees(), self.config.max_pitch_deg)
+                };
+                candidates.push((IncidentKind::TipOver, value, threshold));
+            }
+        } else {
+            self.tilt_since = None;
+        }
+
+        // Caída libre: el acelerómetro deja de medir la gravedad
+        let magnitude = accel.norm();
+        if magnitude < self.config.free_fall_threshold {
+            let since = *self.free_fall_since.get_or_insert(timestamp);
+            if seconds(timestamp - since) >= self.config.free_fall_duration_s {
+                candidates.push((IncidentKind::FreeFall, magnitude, self.config.free_fall_threshold));
+            }
+        } else {
+            self.free_fall_since = None;
+        }
+
+        // Colisión: desviación brusca respecto a la referencia paso bajo
+        match self.baseline.as_mut() {
+            Some(baseline) => {
+                let spike = (accel - *baseline).norm();
+                if spike > self.config.collision_threshold {
+                    candidates.push((IncidentKind::Collision, spike, self.config.collision_threshold));
+                }
+                let alpha = dt / (self.config.baseline_time_constant_s + dt);
+                *baseline += (accel - *baseline) * alpha;
+            }
+            None => self.baseline = Some(accel),
+        }
+
+        candidates.into_iter()
+            .filter(|(kind, _, _)| self.take_cooldown(*kind, timestamp))
+            .map(|(kind, value, threshold)| IncidentDetection { timestamp, kind, value, threshold })
+            .collect()
+    }
+
+    fn take_cooldown(&mut self, kind: IncidentKind, timestamp: chrono::DateTime<chrono::Utc>) -> bool {
+        let ready = self.last_incident.get(&kind)
+            .is_none_or(|last| seconds(timestamp - *last) >= self.config.cooldown_s);
+        if ready {
+            self.last_incident.insert(kind, timestamp);
+        }
+        ready
+    }
+}
+
+fn seconds(duration: chrono::Duration) -> f64 {
+    duration.num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::UnitQuaternion;
+
+    fn imu(accel: Vector3<f64>, roll: f64) -> ImuData {
+        ImuData {
+            linear_acceleration: accel,
+            angular_velocity: Vector3::zeros(),
+            orientation: UnitQuaternion::from_euler_angles(roll, 0.0, 0.0),
+            magnetic_field: None,
+            temperature: 25.0,
+        }
+    }
+
+    fn at(ms: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn test_tip_over_requires_sustained_tilt() {
+        let mut monitor = StabilityMonitor::new(StabilityConfig::default());
+        let gravity = Vector3::new(0.0, 0.0, 9.81);
+        let tilted = 40f64.to_radians();
+
+        assert!(monitor.update(&imu(gravity, tilted), at(0)).is_empty());
+        assert!(monitor.update(&imu(gravity, 0.0), at(100)).is_empty()); // Bache: vuelve a nivel
+        assert!(monitor.update(&imu(gravity, tilted), at(200)).is_empty());
+
+        let detections = monitor.update(&imu(gravity, tilted), at(450));
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].kind, IncidentKind::TipOver);
+        assert!((detections[0].value - 40.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_collision_spike_and_cooldown() {
+        let mut monitor = StabilityMonitor::new(StabilityConfig::default());
+        let gravity = Vector3::new(0.0, 0.0, 9.81);
+
+        // Aceleración suave del robot: la referencia la absorbe
+        for i in 0..100 {
+            let accel = gravity + Vector3::x() * (i as f64 * 0.02);
+            assert!(monitor.update(&imu(accel, 0.0), at(i * 10)).is_empty());
+        }
+
+        let impact = gravity + Vector3::new(-25.0, 3.0, 0.0);
+        let detections = monitor.update(&imu(impact, 0.0), at(1000));
+        assert_eq!(detections.iter().map(|d| d.kind).collect::<Vec<_>>(), vec![IncidentKind::Collision]);
+
+        // Rebote inmediato: dentro del periodo de espera
+        assert!(monitor.update(&imu(gravity - impact, 0.0), at(1010)).is_empty());
+    }
+
+    #[test]
+    fn test_free_fall_and_sequence_dedup() {
+        let mut monitor = StabilityMonitor::new(StabilityConfig::default());
+        let sample = |sequence: u64, ms: i64, accel: Vector3<f64>| Sample {
+            timestamp: at(ms),
+            hardware_timestamp: None,
+            received_at: at(ms),
+            sequence,
+            latency: std::time::Duration::ZERO,
+            value: imu(accel, 0.0),
+        };
+
+        let samples: Vec<_> = (0..10)
+            .map(|i| sample(i, i as i64 * 20, if i < 3 { Vector3::new(0.0, 0.0, 9.81) } else { Vector3::new(0.0, 0.0, 0.5) }))
+            .collect();
+        let detections = monitor.process(&samples);
+        assert!(detections.iter().any(|d| d.kind == IncidentKind::FreeFall));
+
+        // Las mismas muestras no se evalúan dos veces
+        assert!(monitor.process(&samples).is_empty());
+    }
+}
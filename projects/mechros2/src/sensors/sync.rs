@@ -0,0 +1,172 @@
+// 🕰️ Sensor Time Synchronization Module
+// File: projects/mechros2/src/sensors/sync.rs
+
+use std::time::Duration;
+use super::acquisition::Sample;
+use super::odometry::OdometryEstimate;
+use super::orientation::wrap_angle;
+use super::{FilteredScan, GpsData, ImuData};
+
+/// Interpolación lineal entre dos muestras consecutivas (`t` en [0, 1]).
+pub trait Interpolate {
+    fn interpolate(&self, next: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for ImuData {
+    fn interpolate(&self, next: &Self, t: f64) -> Self {
+        Self {
+            linear_acceleration: self.linear_acceleration.lerp(&next.linear_acceleration, t),
+            angular_velocity: self.angular_velocity.lerp(&next.angular_velocity, t),
+            orientation: self.orientation.slerp(&next.orientation, t),
+            magnetic_field: match (self.magnetic_field, next.magnetic_field) {
+                (Some(a), Some(b)) => Some(a.lerp(&b, t)),
+                (a, b) => a.or(b),
+            },
+            temperature: self.temperature + (next.temperature - self.temperature) * t as f32,
+        }
+    }
+}
+
+impl Interpolate for OdometryEstimate {
+    fn interpolate(&self, next: &Self, t: f64) -> Self {
+        // El rumbo se interpola por el camino corto
+        let delta = wrap_angle(next.heading - self.heading);
+
+        Self {
+            position: self.position + (next.position - self.position) * t,
+            heading: wrap_angle(self.heading + delta * t),
+            linear_velocity: self.linear_velocity.lerp(&next.linear_velocity, t),
+            angular_velocity: self.angular_velocity + (next.angular_velocity - self.angular_velocity) * t,
+            distance_traveled: self.distance_traveled + (next.distance_traveled - self.distance_traveled) * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// Separación máxima entre las dos muestras que rodean el instante pedido.
+    pub max_interpolation_gap: Duration,
+    /// Margen fuera del historial en el que se acepta la muestra del extremo.
+    pub edge_tolerance: Duration,
+    /// Diferencia máxima para emparejar por la muestra más cercana.
+    pub max_nearest_offset: Duration,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            max_interpolation_gap: Duration::from_millis(200),
+            edge_tolerance: Duration::from_millis(20),
+            max_nearest_offset: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Valores de los sensores referidos a un mismo instante.
+#[derive(Debug, Clone)]
+pub struct SynchronizedData {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub imu: Option<ImuData>,                 // Interpolado
+    pub odometry: Option<OdometryEstimate>,   // Interpolado
+    pub lidar: Option<Sample<FilteredScan>>,  // Muestra más cercana
+    pub gps: Option<Sample<GpsData>>,         // Muestra más cercana
+}
+
+fn offset_secs(a: chrono::DateTime<chrono::Utc>, b: chrono::DateTime<chrono::Utc>) -> f64 {
+    (a - b).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+}
+
+/// Valor interpolado en `at` a partir de un historial ordenado por tiempo.
+pub fn interpolate_at<T: Interpolate + Clone>(
+    samples: &[Sample<T>],
+    at: chrono::DateTime<chrono::Utc>,
+    config: &SyncConfig,
+) -> Option<T> {
+    let first = samples.first()?;
+    let last = samples.last()?;
+    let tolerance = config.edge_tolerance.as_secs_f64();
+
+    if at <= first.timestamp {
+        return (offset_secs(first.timestamp, at) <= tolerance).then(|| first.value.clone());
+    }
+    if at >= last.timestamp {
+        return (offset_secs(at, last.timestamp) <= tolerance).then(|| last.value.clone());
+    }
+
+    let next = samples.partition_point(|s| s.timestamp <= at);
+    let (a, b) = (&samples[next - 1], &samples[next]);
+    let gap = offset_secs(b.timestamp, a.timestamp);
+    if gap > config.max_interpolation_gap.as_secs_f64() {
+        return None;
+    }
+
+    let t = if gap > 0.0 { offset_secs(at, a.timestamp) / gap } else { 0.0 };
+    Some(a.value.interpolate(&b.value, t))
+}
+
+/// Muestra más cercana a `at`, si está dentro de `max_offset`.
+pub fn nearest_at<T: Clone>(
+    samples: &[Sample<T>],
+    at: chrono::DateTime<chrono::Utc>,
+    max_offset: Duration,
+) -> Option<Sample<T>> {
+    samples.iter()
+        .map(|s| (offset_secs(s.timestamp, at).abs(), s))
+        .filter(|(offset, _)| *offset <= max_offset.as_secs_f64())
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, s)| s.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector3};
+
+    fn at(ms: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    fn sample<T>(ms: i64, value: T) -> Sample<T> {
+        Sample {
+            timestamp: at(ms),
+            hardware_timestamp: None,
+            received_at: at(ms),
+            sequence: ms as u64,
+            latency: Duration::ZERO,
+            value,
+        }
+    }
+
+    fn odometry(x: f64, heading: f64) -> OdometryEstimate {
+        OdometryEstimate {
+            position: Point3::new(x, 0.0, 0.0),
+            heading,
+            linear_velocity: Vector3::zeros(),
+            angular_velocity: 0.0,
+            distance_traveled: x,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_odometry() {
+        let samples = vec![sample(0, odometry(0.0, 3.0)), sample(100, odometry(1.0, -3.0))];
+        let config = SyncConfig::default();
+
+        let mid = interpolate_at(&samples, at(25), &config).unwrap();
+        assert!((mid.position.x - 0.25).abs() < 1e-9);
+        // De 3.0 a -3.0 rad se pasa por ±π, no por cero
+        assert!(mid.heading > 3.0);
+
+        assert!(interpolate_at(&samples, at(110), &config).unwrap().position.x == 1.0);
+        assert!(interpolate_at(&samples, at(200), &config).is_none());
+    }
+
+    #[test]
+    fn test_nearest_match() {
+        let samples = vec![sample(0, 'a'), sample(100, 'b'), sample(200, 'c')];
+
+        assert_eq!(nearest_at(&samples, at(140), Duration::from_millis(100)).unwrap().value, 'b');
+        assert_eq!(nearest_at(&samples, at(160), Duration::from_millis(100)).unwrap().value, 'c');
+        assert!(nearest_at(&samples, at(400), Duration::from_millis(100)).is_none());
+    }
+}
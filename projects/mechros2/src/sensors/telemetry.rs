@@ -0,0 +1,510 @@
+// 📡 Telemetry Encoding Module
+// File: projects/mechros2/src/sensors/telemetry.rs
+//
+// Reduce el ancho de banda de la telemetría hacia el dashboard: cada grupo de
+// campos se publica a su propia frecuencia, el barrido LiDAR se cuantiza y se
+// envía como diferencias respecto al anterior, y la trama puede codificarse en
+// CBOR o MessagePack además de JSON.
+
+use std::collections::HashMap;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use super::alarms::AlarmAction;
+use super::battery::BatteryEstimate;
+use super::compass::HeadingEstimate;
+use super::health::SensorKind;
+use super::odometry::OdometryEstimate;
+use super::proximity::ProximityReading;
+use super::{GpsData, ImuData, LidarData, SensorData};
+
+/// Valor cuantizado reservado para haces sin retorno (infinito o NaN).
+const NO_RETURN: i32 = u16::MAX as i32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TelemetryEncoding {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl TelemetryEncoding {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TelemetryEncoding::Json => "application/json",
+            TelemetryEncoding::Cbor => "application/cbor",
+            TelemetryEncoding::MessagePack => "application/msgpack",
+        }
+    }
+}
+
+/// Grupos de campos de `SensorData` con frecuencia de publicación propia.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TelemetryField {
+    Pose,        // Posición, velocidad, orientación, rumbo y odometría
+    Imu,
+    Gps,
+    Lidar,
+    Environment, // Temperatura, presión y luz
+    Proximity,
+    Battery,
+    Status,      // Acción ambiental, sensores caducados y marcas de tiempo
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanEncodingConfig {
+    pub range_resolution: f32,     // m por unidad cuantizada
+    pub intensity_resolution: f32,
+    /// Barridos entre fotogramas completos; el resto se envía como diferencias.
+    /// Con 0 todos los barridos son completos.
+    pub keyframe_interval: u32,
+}
+
+impl Default for ScanEncodingConfig {
+    fn default() -> Self {
+        Self {
+            range_resolution: 0.01,
+            intensity_resolution: 1.0,
+            keyframe_interval: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Frecuencia máxima de publicación por grupo (Hz); 0 o ausente = no se publica.
+    pub rates: HashMap<TelemetryField, f64>,
+    pub encoding: TelemetryEncoding,
+    pub scan: ScanEncodingConfig,
+}
+
+impl TelemetryConfig {
+    pub fn rate(&self, field: TelemetryField) -> f64 {
+        self.rates.get(&field).copied().unwrap_or(0.0)
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            rates: HashMap::from([
+                (TelemetryField::Pose, 20.0),
+                (TelemetryField::Imu, 10.0),
+                (TelemetryField::Gps, 5.0),
+                (TelemetryField::Lidar, 2.0),
+                (TelemetryField::Environment, 1.0),
+                (TelemetryField::Proximity, 5.0),
+                (TelemetryField::Battery, 1.0),
+                (TelemetryField::Status, 1.0),
+            ]),
+            // JSON por defecto: es lo que publica `/mechros2/telemetry` y espera el dashboard
+            encoding: TelemetryEncoding::Json,
+            scan: ScanEncodingConfig::default(),
+        }
+    }
+}
+
+/// Barrido cuantizado. En los fotogramas delta cada valor es la diferencia con
+/// el barrido anterior, de modo que las zonas estáticas se codifican como ceros.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactScan {
+    pub sequence: u32,
+    pub keyframe: bool,
+    pub angle_min: f32,
+    pub angle_increment: f32,
+    pub range_max: f32,
+    pub range_resolution: f32,
+    pub intensity_resolution: f32,
+    pub ranges: Vec<i32>,
+    pub intensities: Vec<i32>,
+}
+
+/// Trama de telemetría: solo contiene los grupos que tocaba publicar.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryFrame {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<Point3<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<Vector3<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "super::orientation::rpy_serde_option")]
+    pub orientation: Option<UnitQuaternion<f64>>, // {roll, pitch, yaw}, como `SystemState`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<HeadingEstimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub odometry: Option<OdometryEstimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imu: Option<ImuData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps: Option<GpsData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lidar: Option<CompactScan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light_level: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proximity: Option<Vec<ProximityReading>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery: Option<BatteryEstimate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment_action: Option<AlarmAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_sensors: Option<Vec<SensorKind>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_timestamps: Option<HashMap<SensorKind, chrono::DateTime<chrono::Utc>>>,
+}
+
+/// Trama ya serializada, con su tipo de contenido.
+#[derive(Debug, Clone)]
+pub struct TelemetryPayload {
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+impl TelemetryPayload {
+    pub fn encode(frame: &TelemetryFrame, encoding: TelemetryEncoding) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = match encoding {
+            TelemetryEncoding::Json => serde_json::to_vec(frame)?,
+            TelemetryEncoding::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(frame, &mut bytes)?;
+                bytes
+            }
+            // Mapas con nombre de campo para que el dashboard no dependa del orden
+            TelemetryEncoding::MessagePack => rmp_serde::to_vec_named(frame)?,
+        };
+
+        Ok(Self {
+            content_type: encoding.content_type(),
+            bytes,
+        })
+    }
+}
+
+struct ScanEncoder {
+    config: ScanEncodingConfig,
+    previous: Option<(Vec<i32>, Vec<i32>)>,
+    since_keyframe: u32,
+    sequence: u32,
+}
+
+impl ScanEncoder {
+    fn new(config: ScanEncodingConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+            since_keyframe: 0,
+            sequence: 0,
+        }
+    }
+
+    fn encode(&mut self, scan: &LidarData) -> CompactScan {
+        let ranges: Vec<i32> = scan.ranges.iter()
+            .map(|&r| quantize(r, self.config.range_resolution))
+            .collect();
+        let intensities: Vec<i32> = scan.intensities.iter()
+            .map(|&i| quantize(i, self.config.intensity_resolution))
+            .collect();
+
+        // Fotograma completo periódicamente o si cambia el número de haces
+        let keyframe = match &self.previous {
+            Some((prev_ranges, prev_intensities)) => {
+                self.since_keyframe >= self.config.keyframe_interval
+                    || prev_ranges.len() != ranges.len()
+                    || prev_intensities.len() != intensities.len()
+            }
+            None => true,
+        };
+
+        let (encoded_ranges, encoded_intensities) = match (&self.previous, keyframe) {
+            (Some((prev_ranges, prev_intensities)), false) => (delta(&ranges, prev_ranges), delta(&intensities, prev_intensities)),
+            _ => (ranges.clone(), intensities.clone()),
+        };
+
+        self.since_keyframe = if keyframe { 1 } else { self.since_keyframe + 1 };
+        self.previous = Some((ranges, intensities));
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        CompactScan {
+            sequence,
+            keyframe,
+            angle_min: scan.angle_min,
+            angle_increment: scan.angle_increment,
+            range_max: scan.range_max,
+            range_resolution: self.config.range_resolution,
+            intensity_resolution: self.config.intensity_resolution,
+            ranges: encoded_ranges,
+            intensities: encoded_intensities,
+        }
+    }
+}
+
+/// Reconstruye los barridos en el receptor (dashboard, registros).
+#[derive(Default)]
+pub struct ScanDecoder {
+    previous: Option<(u32, Vec<i32>, Vec<i32>)>,
+}
+
+impl ScanDecoder {
+    /// Devuelve (distancias, intensidades); `None` si llega un delta sin su
+    /// barrido de referencia (se perdió un mensaje) hasta el siguiente fotograma completo.
+    pub fn decode(&mut self, scan: &CompactScan) -> Option<(Vec<f32>, Vec<f32>)> {
+        let (ranges, intensities) = if scan.keyframe {
+            (scan.ranges.clone(), scan.intensities.clone())
+        } else {
+            match &self.previous {
+                Some((sequence, ranges, intensities))
+                    if sequence.wrapping_add(1) == scan.sequence && ranges.len() == scan.ranges.len() =>
+                {
+                    (undelta(&scan.ranges, ranges), undelta(&scan.intensities, intensities))
+                }
+                _ => {
+                    self.previous = None;
+                    return None;
+                }
+            }
+        };
+
+        let decoded = (
+            ranges.iter().map(|&q| dequantize(q, scan.range_resolution)).collect(),
+            intensities.iter().map(|&q| dequantize(q, scan.intensity_resolution)).collect(),
+        );
+        self.previous = Some((scan.sequence, ranges, intensities));
+        Some(decoded)
+    }
+}
+
+/// Decide qué grupos toca publicar en cada ciclo y construye la trama.
+pub struct TelemetryEncoder {
+    config: TelemetryConfig,
+    last_published: HashMap<TelemetryField, chrono::DateTime<chrono::Utc>>,
+    scan_encoder: ScanEncoder,
+}
+
+impl TelemetryEncoder {
+    pub fn new(config: TelemetryConfig) -> Self {
+        Self {
+            scan_encoder: ScanEncoder::new(config.scan.clone()),
+            config,
+            last_published: HashMap::new(),
+        }
+    }
+
+    /// Trama con los grupos vencidos, ya serializada; `None` si no toca publicar nada.
+    pub fn encode(&mut self, data: &SensorData) -> Result<Option<TelemetryPayload>, Box<dyn std::error::Error>> {
+        match self.frame(data) {
+            Some(frame) => Ok(Some(TelemetryPayload::encode(&frame, self.config.encoding)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn frame(&mut self, data: &SensorData) -> Option<TelemetryFrame> {
+        let mut frame = TelemetryFrame {
+            timestamp: data.timestamp,
+            ..TelemetryFrame::default()
+        };
+        let mut any = false;
+
+        // Un grupo sin datos no consume su turno: se enviará en cuanto los haya
+        if (data.position.is_some() || data.orientation.is_some() || data.odometry.is_some())
+            && self.due(TelemetryField::Pose, data.timestamp)
+        {
+            frame.position = data.position;
+            frame.velocity = data.velocity;
+            frame.orientation = data.orientation;
+            frame.heading = data.heading.clone();
+            frame.odometry = data.odometry.clone();
+            any = true;
+        }
+        if data.imu_data.is_some() && self.due(TelemetryField::Imu, data.timestamp) {
+            frame.imu = data.imu_data.clone();
+            any = true;
+        }
+        if data.gps_data.is_some() && self.due(TelemetryField::Gps, data.timestamp) {
+            frame.gps = data.gps_data.clone();
+            any = true;
+        }
+        if let Some(scan) = &data.lidar_data {
+            if self.due(TelemetryField::Lidar, data.timestamp) {
+                frame.lidar = Some(self.scan_encoder.encode(scan));
+                any = true;
+            }
+        }
+        if (data.temperature.is_some() || data.pressure.is_some() || data.light_level.is_some())
+            && self.due(TelemetryField::Environment, data.timestamp)
+        {
+            frame.temperature = data.temperature;
+            frame.pressure = data.pressure;
+            frame.light_level = data.light_level;
+            any = true;
+        }
+        if !data.proximity_sensors.is_empty() && self.due(TelemetryField::Proximity, data.timestamp) {
+            frame.proximity = Some(data.proximity_sensors.clone());
+            any = true;
+        }
+        if data.battery.is_some() && self.due(TelemetryField::Battery, data.timestamp) {
+            frame.battery = data.battery.clone();
+            any = true;
+        }
+        if self.due(TelemetryField::Status, data.timestamp) {
+            frame.environment_action = Some(data.environment_action);
+            frame.stale_sensors = Some(data.stale_sensors.clone());
+            frame.sample_timestamps = Some(data.sample_timestamps.clone());
+            any = true;
+        }
+
+        any.then_some(frame)
+    }
+
+    fn due(&mut self, field: TelemetryField, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let rate = self.config.rate(field);
+        if rate <= 0.0 {
+            return false;
+        }
+
+        let period_ms = 1000.0 / rate;
+        let due = self.last_published.get(&field).is_none_or(|last| {
+            // Pequeña tolerancia para no perder un turno por jitter del bucle
+            (now - *last).num_milliseconds() as f64 >= period_ms * 0.95
+        });
+        if due {
+            self.last_published.insert(field, now);
+        }
+        due
+    }
+}
+
+fn quantize(value: f32, resolution: f32) -> i32 {
+    if !value.is_finite() || resolution <= 0.0 {
+        return NO_RETURN;
+    }
+    ((value / resolution).round() as i32).clamp(0, NO_RETURN - 1)
+}
+
+fn dequantize(value: i32, resolution: f32) -> f32 {
+    if value >= NO_RETURN {
+        f32::INFINITY
+    } else {
+        value as f32 * resolution
+    }
+}
+
+fn delta(current: &[i32], previous: &[i32]) -> Vec<i32> {
+    current.iter().zip(previous).map(|(c, p)| c - p).collect()
+}
+
+fn undelta(delta: &[i32], previous: &[i32]) -> Vec<i32> {
+    delta.iter().zip(previous).map(|(d, p)| p + d).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(ranges: Vec<f32>) -> LidarData {
+        LidarData {
+            intensities: vec![100.0; ranges.len()],
+            ranges,
+            angle_min: -std::f32::consts::PI,
+            angle_max: std::f32::consts::PI,
+            angle_increment: 0.1,
+            range_min: 0.1,
+            range_max: 30.0,
+        }
+    }
+
+    #[test]
+    fn test_scan_delta_roundtrip() {
+        let mut encoder = ScanEncoder::new(ScanEncodingConfig { keyframe_interval: 3, ..ScanEncodingConfig::default() });
+        let mut decoder = ScanDecoder::default();
+
+        let scans = [
+            vec![1.0, 2.5, f32::INFINITY, 4.0],
+            vec![1.0, 2.52, 3.0, f32::INFINITY],
+            vec![1.01, 2.52, 3.0, f32::INFINITY],
+            vec![1.02, 2.5, 3.1, 4.0],
+        ];
+        let encoded: Vec<CompactScan> = scans.iter().map(|r| encoder.encode(&scan(r.clone()))).collect();
+        assert_eq!(encoded.iter().map(|s| s.keyframe).collect::<Vec<_>>(), vec![true, false, false, true]);
+        // Haces sin cambios se envían como ceros
+        assert_eq!(encoded[2].ranges, vec![1, 0, 0, 0]);
+
+        for (original, compact) in scans.iter().zip(&encoded) {
+            let (ranges, _) = decoder.decode(compact).unwrap();
+            for (a, b) in original.iter().zip(&ranges) {
+                assert!(a == b || (a - b).abs() <= 0.005);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoder_waits_for_keyframe_after_loss() {
+        let mut encoder = ScanEncoder::new(ScanEncodingConfig::default());
+        let mut decoder = ScanDecoder::default();
+
+        let first = encoder.encode(&scan(vec![1.0, 2.0]));
+        let _lost = encoder.encode(&scan(vec![1.1, 2.0]));
+        let third = encoder.encode(&scan(vec![1.2, 2.0]));
+
+        assert!(decoder.decode(&first).is_some());
+        assert!(decoder.decode(&third).is_none());
+    }
+
+    #[test]
+    fn test_frame_orientation_serialized_as_rpy() {
+        let q = UnitQuaternion::from_euler_angles(0.1, -0.2, 1.0);
+        let frame = TelemetryFrame {
+            orientation: Some(q),
+            imu: Some(ImuData {
+                linear_acceleration: Vector3::new(0.0, 0.0, 9.81),
+                angular_velocity: Vector3::zeros(),
+                orientation: q,
+                magnetic_field: None,
+                temperature: 25.0,
+            }),
+            ..TelemetryFrame::default()
+        };
+
+        let json = serde_json::to_value(&frame).unwrap();
+        assert!((json["orientation"]["yaw"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+        assert!((json["imu"]["orientation"]["pitch"].as_f64().unwrap() + 0.2).abs() < 1e-9);
+
+        let back: TelemetryFrame = serde_json::from_value(json).unwrap();
+        assert!(back.orientation.unwrap().angle_to(&q) < 1e-9);
+
+        // Sin orientación el campo se omite y se recupera como `None`
+        let empty = serde_json::to_value(TelemetryFrame::default()).unwrap();
+        assert!(empty.get("orientation").is_none());
+        assert!(serde_json::from_value::<TelemetryFrame>(empty).unwrap().orientation.is_none());
+    }
+
+    #[test]
+    fn test_default_encoding_is_json() {
+        let frame = TelemetryFrame::default();
+        let payload = TelemetryPayload::encode(&frame, TelemetryConfig::default().encoding).unwrap();
+        assert_eq!(payload.content_type, "application/json");
+        assert!(String::from_utf8(payload.bytes).is_ok());
+    }
+
+    #[test]
+    fn test_binary_encodings_are_smaller_than_json() {
+        let mut encoder = ScanEncoder::new(ScanEncodingConfig::default());
+        let frame = TelemetryFrame {
+            lidar: Some(encoder.encode(&scan((0..360).map(|i| 1.0 + (i % 50) as f32 * 0.1).collect()))),
+            ..TelemetryFrame::default()
+        };
+
+        let json = TelemetryPayload::encode(&frame, TelemetryEncoding::Json).unwrap();
+        let cbor = TelemetryPayload::encode(&frame, TelemetryEncoding::Cbor).unwrap();
+        let msgpack = TelemetryPayload::encode(&frame, TelemetryEncoding::MessagePack).unwrap();
+        assert_eq!(cbor.content_type, "application/cbor");
+        assert!(cbor.bytes.len() < json.bytes.len());
+        assert!(msgpack.bytes.len() < json.bytes.len());
+
+        let decoded: TelemetryFrame = ciborium::de::from_reader(cbor.bytes.as_slice()).unwrap();
+        assert_eq!(decoded.lidar.unwrap().ranges, frame.lidar.unwrap().ranges);
+    }
+}